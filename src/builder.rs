@@ -0,0 +1,369 @@
+use std::fs::{create_dir_all, File};
+use std::io::{Error, ErrorKind, Result, Write};
+use std::path::{Path, PathBuf};
+
+use crate::diff::{tree_diff, Difference};
+use crate::emitter::library_writer;
+use crate::error::BuilderError;
+use crate::ir::{CodeList, Library};
+use crate::output::{install, staging_dir};
+use crate::report::Report;
+use crate::parser::{code_list_files, code_list_linker, code_list_parser, module_parser, single_item_linker, Context, ModuleNaming};
+use crate::schema::{dependency_order, schema_dir_files, schema_file_name};
+
+
+// Files written to crate directory when manifest is enabled
+const MANIFEST_FILES: [&str; 4] = ["Cargo.toml", "README.md", "LICENSE", ".gitignore"];
+
+// Static files copied to the generated crate
+const README: &str = include_str!("../ubl-rs-out/README.md");
+const LICENSE: &str = include_str!("../ubl-rs-out/LICENSE");
+const GITIGNORE: &str = include_str!("../ubl-rs-out/.gitignore");
+
+
+/// Generates UBL types from JSON schema files.
+///
+/// By default sources are written straight into `out_dir` (or `OUT_DIR` env variable when
+/// run from build script) with `lib.rs` as the entry file. Enable [`Builder::manifest`] to
+/// create a standalone crate with `Cargo.toml` and sources in `src` directory instead.
+#[derive(Debug, Clone)]
+pub struct Builder {
+    schema_dirs: Vec<PathBuf>,
+    schemas: Vec<PathBuf>,
+    code_list_dirs: Vec<PathBuf>,
+    out_dir: Option<PathBuf>,
+    root_mod: String,
+    naming: ModuleNaming,
+    manifest: bool,
+    exporter: bool,
+    typestate_builders: bool,
+    reference_newtypes: bool,
+    collapse_single_items: bool,
+    crate_name: String,
+    crate_version: String,
+    edition: String,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            schema_dirs: vec![],
+            schemas: vec![],
+            code_list_dirs: vec![],
+            out_dir: None,
+            root_mod: String::from("crate"),
+            naming: ModuleNaming::default(),
+            manifest: false,
+            exporter: false,
+            typestate_builders: false,
+            reference_newtypes: false,
+            collapse_single_items: false,
+            crate_name: String::from("ubl-rs"),
+            crate_version: String::from("0.1.0"),
+            edition: String::from("2021"),
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Directory to discover `.json` schema files from, can be called multiple times
+    /// e.g. for UBL `common` and `maindoc` schema directories
+    pub fn schema_dir<P>(mut self, dir: P) -> Self where P: Into<PathBuf> {
+        self.schema_dirs.push(dir.into());
+        self
+    }
+    /// Additional schema file to process with the ones found from schema directory
+    pub fn schema<P>(mut self, file: P) -> Self where P: Into<PathBuf> {
+        self.schemas.push(file.into());
+        self
+    }
+    /// Directory to read Genericode `.gc` code list files from, can be called multiple times.
+    /// Each list is generated as enum and qualified data type named after it, e.g.
+    /// `Currency_CodeType` for `CurrencyCode`, validates its value against the list.
+    pub fn code_list_dir<P>(mut self, dir: P) -> Self where P: Into<PathBuf> {
+        self.code_list_dirs.push(dir.into());
+        self
+    }
+    /// Directory to write generated sources to, defaults to `OUT_DIR` env variable
+    pub fn out_dir<P>(mut self, dir: P) -> Self where P: Into<PathBuf> {
+        self.out_dir = Some(dir.into());
+        self
+    }
+    /// Rust path of the module that includes generated `lib.rs`, e.g. `crate::ubl`
+    /// when included with `mod ubl { include!(...); }`. Defaults to `crate`.
+    pub fn root_module<T>(mut self, path: T) -> Self where T: Into<String> {
+        self.root_mod = path.into();
+        self
+    }
+    /// How schema files are named as modules
+    pub fn module_naming(mut self, naming: ModuleNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+    /// Write `Cargo.toml`, README, LICENSE and .gitignore files and place sources in `src` directory
+    pub fn manifest(mut self, enabled: bool) -> Self {
+        self.manifest = enabled;
+        self
+    }
+    /// Declare `exporter` module in `lib.rs` and create empty `exporter.rs` file if missing
+    pub fn exporter(mut self, enabled: bool) -> Self {
+        self.exporter = enabled;
+        self
+    }
+    /// Generate `builder()` for every component, returning builder that takes the fields by name
+    /// and has `build()` only when all required fields are set
+    pub fn typestate_builders(mut self, enabled: bool) -> Self {
+        self.typestate_builders = enabled;
+        self
+    }
+    /// Generate references like `ID` and `Name` of basic components as newtypes of their target
+    /// instead of type aliases, so that they can't be mixed up with each other
+    pub fn reference_newtypes(mut self, enabled: bool) -> Self {
+        self.reference_newtypes = enabled;
+        self
+    }
+    /// Generate array properties with `maxItems: 1` as the item or optional item instead of array
+    /// wrapper struct, they're still written as one item JSON arrays
+    pub fn collapse_single_items(mut self, enabled: bool) -> Self {
+        self.collapse_single_items = enabled;
+        self
+    }
+    /// Package name written to `Cargo.toml` when manifest is enabled
+    pub fn crate_name<T>(mut self, name: T) -> Self where T: Into<String> {
+        self.crate_name = name.into();
+        self
+    }
+    /// Package version written to `Cargo.toml` when manifest is enabled
+    pub fn crate_version<T>(mut self, version: T) -> Self where T: Into<String> {
+        self.crate_version = version.into();
+        self
+    }
+    /// Rust edition written to `Cargo.toml` when manifest is enabled
+    pub fn edition<T>(mut self, edition: T) -> Self where T: Into<String> {
+        self.edition = edition.into();
+        self
+    }
+    /// Schema files in the order they are processed, referenced schemas before the ones referencing them
+    pub fn schema_files(&self) -> std::result::Result<Vec<PathBuf>, BuilderError> {
+        let mut files = vec![];
+
+        for dir in self.schema_dirs.iter() {
+            files.extend(schema_dir_files(dir)?);
+        }
+
+        for file in self.schemas.iter() {
+            if !files.contains(file) {
+                files.push(file.to_owned());
+            }
+        }
+
+        dependency_order(files)
+    }
+    /// Code lists from code list directories, in file name order
+    fn code_lists(&self) -> std::result::Result<Vec<CodeList>, BuilderError> {
+        let mut code_lists: Vec<CodeList> = vec![];
+
+        for dir in self.code_list_dirs.iter() {
+            for file in code_list_files(dir)? {
+                let code_list = code_list_parser(&file)?;
+
+                if code_lists.iter().any(|c|c.name.eq(&code_list.name)) {
+                    return Err(BuilderError::DuplicateCodeList(code_list.short_name))
+                }
+
+                code_lists.push(code_list);
+            }
+        }
+
+        Ok(code_lists)
+    }
+    /// Name of the module generated from given schema file
+    pub fn module_name<P>(&self, schema_file: P) -> String where P: AsRef<Path> {
+        self.naming.module_name(schema_file_name(schema_file))
+    }
+    /// Generate sources from schema files. Output is written to a staging directory and
+    /// swapped in only when everything is generated, files not generated by the builder
+    /// (e.g. `exporter.rs`) are preserved. Modules generated by earlier runs but not by this
+    /// one are removed. Returned report lists schema keywords that
+    /// were not handled.
+    pub fn generate(&self) -> std::result::Result<Report, BuilderError> {
+        let root_mod = syn::parse_str::<syn::Path>(&self.root_mod)
+            .map_err(|_|BuilderError::RootModule(self.root_mod.to_owned()))?;
+
+        let schema_files = self.schema_files()?;
+        let out_path = self.out_path()?;
+
+        let ctx = Context {
+            root_mod: &root_mod,
+            naming: self.naming,
+            schemas: &schema_files,
+            typestate_builders: self.typestate_builders,
+            reference_newtypes: self.reference_newtypes,
+            collapse_single_items: self.collapse_single_items,
+        };
+
+        // Read schemas into library before touching the output
+        let mut library = Library::default();
+        let mut report = Report::default();
+        let mut errors = vec![];
+
+        for in_path in schema_files.iter() {
+            library.modules.push(module_parser(in_path, &ctx, &mut errors, &mut report.unhandled)?);
+        }
+
+        if !errors.is_empty() {
+            return Err(BuilderError::Schema(errors))
+        }
+
+        library.code_lists = self.code_lists()?;
+        code_list_linker(&mut library.modules, &library.code_lists);
+
+        if self.collapse_single_items {
+            single_item_linker(&mut library.modules);
+        }
+
+        let staging = staging_dir(&out_path)?;
+        let mut entries = vec![];
+
+        let src_dir = match self.manifest {
+            true => {
+                self.manifest_writer(staging.path())?;
+                entries.extend(MANIFEST_FILES.map(PathBuf::from));
+                PathBuf::from("src")
+            },
+            false => PathBuf::new(),
+        };
+
+        let src_path = staging.path().join(&src_dir);
+        create_dir_all(&src_path)?;
+
+        library_writer(&src_path, &library, &ctx, self.exporter)?;
+
+        entries.push(src_dir.join("lib.rs"));
+        entries.extend(library.modules.iter().map(|m|src_dir.join(&m.name)));
+
+        if !library.code_lists.is_empty() {
+            entries.push(src_dir.join("code_lists"));
+        }
+
+        // Create empty exporter.rs file if missing, existing one is never replaced
+        let exporter_rs = src_dir.join("exporter.rs");
+        let mut created = vec![];
+
+        if self.exporter && !out_path.join(&exporter_rs).exists() {
+            File::create(staging.path().join(&exporter_rs))?;
+            created.push(exporter_rs);
+        }
+
+        install(staging.path(), &out_path, &entries, &created)?;
+
+        Ok(report)
+    }
+
+    /// Generate into temporary directory and compare the result with existing output in `out_dir`,
+    /// nothing is written to `out_dir`. Empty when the existing output is up to date.
+    pub fn diff(&self) -> std::result::Result<Vec<Difference>, BuilderError> {
+        let out_path = self.out_path()?;
+        let temp_dir = tempfile::tempdir()?;
+
+        self.clone()
+            .out_dir(temp_dir.path())
+            .generate()?;
+
+        // Only sources are compared from existing crate, it has target directory etc. as well
+        let src_dir = match self.manifest {
+            true => "src",
+            false => "",
+        };
+
+        Ok(tree_diff(&out_path, temp_dir.path(), src_dir)?)
+    }
+
+    fn out_path(&self) -> Result<PathBuf> {
+        match &self.out_dir {
+            Some(p) => Ok(p.to_owned()),
+            None => std::env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or(Error::new(ErrorKind::NotFound, "out_dir not set and OUT_DIR env variable is missing")),
+        }
+    }
+
+    fn manifest_writer(&self, out_path: &Path) -> Result<()> {
+        let mut cargo_file = File::create(out_path.join(MANIFEST_FILES[0]))?;
+
+        // Write lib details and dependencies to Cargo.toml file
+        writeln!(cargo_file, "[package]
+name = \"{}\"
+version = \"{}\"
+edition = \"{}\"
+
+# See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+[dependencies]
+thiserror = {{ version = \"1.0.61\" }}
+chrono = {{ version = \"0.4.38\" }}
+regex = {{ version = \"1.10.4\" }}
+serde = {{ version = \"1.0.197\", features = [\"derive\"] }}
+serde_json = \"1.0.115\"", self.crate_name, self.crate_version, self.edition)?;
+
+        // Add readme, license and .gitignore files to crate directory
+        for (file_name, contents) in [(MANIFEST_FILES[1], README), (MANIFEST_FILES[2], LICENSE), (MANIFEST_FILES[3], GITIGNORE)] {
+            std::fs::write(out_path.join(file_name), contents)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_root_module_is_error() {
+        let out_dir = tempfile::tempdir().unwrap();
+        let result = Builder::new()
+            .out_dir(out_dir.path())
+            .root_module("crate::")
+            .generate();
+
+        assert!(matches!(result, Err(BuilderError::RootModule(m)) if m == "crate::"));
+    }
+
+    #[test]
+    fn every_schema_error_is_returned() {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = dir.path().join("Broken-1.0.json");
+        let definitions = serde_json::json!({"definitions": {
+            "CodeType": {
+                "title": 1,
+                "description": true,
+                "properties": {"ID": {"type": 5}},
+                "type": "object"
+            },
+            "Label": {"oneOf": [{"type": "string"}], "title": []}
+        }});
+        std::fs::write(&schema, definitions.to_string()).unwrap();
+
+        let result = Builder::new()
+            .schema(&schema)
+            .out_dir(dir.path().join("out"))
+            .generate();
+
+        let errors = match result {
+            Err(BuilderError::Schema(e)) => e,
+            other => panic!("expected schema errors, got {:?}", other.map_err(|e|e.to_string())),
+        };
+
+        assert!(errors.iter().all(|e|e.file == schema));
+        assert_eq!(errors.iter().map(|e|e.pointer.as_str()).collect::<Vec<&str>>(), [
+            "#/definitions/CodeType/title",
+            "#/definitions/CodeType/description",
+            "#/definitions/CodeType/properties/ID/type",
+            "#/definitions/Label/title",
+        ]);
+    }
+}
@@ -0,0 +1,111 @@
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use ubl_rs_builder::Builder;
+
+
+/// Builds ubl-rs crate from UBL 2.1 JSON schemas
+#[derive(Debug, Parser)]
+#[command(name = "ubl-rs-builder")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Generate ubl-rs crate from schema files
+    Generate(GenerateArgs),
+    /// Check that schema files can be read and parsed without writing anything,
+    /// and optionally that existing crate is up to date
+    Check(CheckArgs),
+    /// Print summary of definitions found from schema files
+    Inspect(InspectArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    /// Directory containing UBL JSON schema files, repeat for multiple directories
+    #[arg(short, long, value_name = "DIR", required = true)]
+    pub schemas: Vec<PathBuf>,
+    /// Directory containing Genericode `.gc` code list files, repeat for multiple directories
+    #[arg(long, value_name = "DIR")]
+    pub code_lists: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct GenerateArgs {
+    #[command(flatten)]
+    pub schema: SchemaArgs,
+    /// Output directory of the generated crate, `src` is created inside it
+    #[arg(short, long, value_name = "DIR")]
+    pub out: PathBuf,
+    #[command(flatten)]
+    pub package: PackageArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    #[command(flatten)]
+    pub schema: SchemaArgs,
+    /// Existing crate to compare with generated one, differences are printed as unified diff
+    #[arg(short, long, value_name = "DIR")]
+    pub out: Option<PathBuf>,
+    #[command(flatten)]
+    pub package: PackageArgs,
+}
+
+/// Options of the generated crate, package details are written to its Cargo.toml file
+#[derive(Debug, Args)]
+pub struct PackageArgs {
+    /// Package name of the generated crate
+    #[arg(long, default_value = "ubl-rs")]
+    pub crate_name: String,
+    /// Package version of the generated crate
+    #[arg(long, default_value = "0.1.0")]
+    pub crate_version: String,
+    /// Rust edition of the generated crate
+    #[arg(long, default_value = "2021")]
+    pub edition: String,
+    /// Generate typestate builders checking at compile time that required fields are set
+    #[arg(long)]
+    pub typestate_builders: bool,
+    /// Generate references (e.g. basic components `ID` and `Name`) as newtypes instead of type aliases
+    #[arg(long)]
+    pub reference_newtypes: bool,
+    /// Generate array properties with `maxItems: 1` as plain fields, written as one item arrays
+    #[arg(long)]
+    pub collapse_single_items: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct InspectArgs {
+    #[command(flatten)]
+    pub schema: SchemaArgs,
+    /// List names of the definitions in addition to the summary
+    #[arg(short, long)]
+    pub definitions: bool,
+}
+
+impl SchemaArgs {
+    /// Builder with schema and code list directories set
+    pub fn builder(&self) -> Builder {
+        let builder = self.schemas.iter().fold(Builder::new(), |b, dir|b.schema_dir(dir));
+        self.code_lists.iter().fold(builder, |b, dir|b.code_list_dir(dir))
+    }
+}
+
+impl PackageArgs {
+    /// Builder for generating the crate to given directory
+    pub fn builder(&self, schema: &SchemaArgs, out: &Path) -> Builder {
+        schema.builder()
+            .out_dir(out)
+            .manifest(true)
+            .exporter(true)
+            .crate_name(&self.crate_name)
+            .crate_version(&self.crate_version)
+            .edition(&self.edition)
+            .typestate_builders(self.typestate_builders)
+            .reference_newtypes(self.reference_newtypes)
+            .collapse_single_items(self.collapse_single_items)
+    }
+}
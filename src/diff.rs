@@ -0,0 +1,100 @@
+use similar::TextDiff;
+use std::collections::BTreeSet;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+use crate::output::ENTRIES_FILE;
+
+
+// Exporter is written by hand into the generated crate, it's never compared. Neither is the
+// list of generated entries.
+const EXPORTER: &str = "exporter.rs";
+
+/// File in existing output that doesn't match the generated one
+#[derive(Debug, Clone)]
+pub struct Difference {
+    /// Path relative to the output directory
+    pub path: PathBuf,
+    pub kind: DifferenceKind,
+    /// Unified diff from existing to generated contents
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DifferenceKind {
+    /// Generated file is missing from existing output
+    Missing,
+    /// Contents of the existing file differ from the generated file
+    Changed,
+    /// Existing source file is no longer generated
+    Stale,
+}
+
+/// Compare generated tree with existing one. Every generated file is compared, but only `.rs`
+/// files under `src_dir` of the existing tree as it may have e.g. build artifacts.
+pub fn tree_diff(existing: &Path, generated: &Path, src_dir: &str) -> Result<Vec<Difference>> {
+    let mut paths = BTreeSet::new();
+
+    paths.extend(tree_files(generated, generated)?);
+
+    if existing.join(src_dir).is_dir() {
+        paths.extend(tree_files(existing, &existing.join(src_dir))?
+            .into_iter()
+            .filter(|p|p.extension().is_some_and(|e|e.eq("rs"))));
+    }
+
+    let mut differences = vec![];
+
+    for path in paths {
+        if path.file_name().is_some_and(|f|f.eq(EXPORTER)) || path.eq(Path::new(ENTRIES_FILE)) {
+            continue;
+        }
+
+        let old = read_lossy(&existing.join(&path))?;
+        let new = read_lossy(&generated.join(&path))?;
+
+        let kind = match (&old, &new) {
+            (None, _) => DifferenceKind::Missing,
+            (_, None) => DifferenceKind::Stale,
+            (Some(o), Some(n)) if o.ne(n) => DifferenceKind::Changed,
+            _ => continue,
+        };
+
+        let old = old.unwrap_or_default();
+        let new = new.unwrap_or_default();
+        let name = path.display().to_string();
+
+        let diff = TextDiff::from_lines(&old, &new)
+            .unified_diff()
+            .header(&format!("a/{}", name), &format!("b/{}", name))
+            .to_string();
+
+        differences.push(Difference { path, kind, diff });
+    }
+
+    Ok(differences)
+}
+
+// Files under the directory, relative to root
+fn tree_files(root: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        match path.is_dir() {
+            true => files.extend(tree_files(root, &path)?),
+            false => files.push(path.strip_prefix(root).unwrap_or(&path).to_path_buf()),
+        }
+    }
+
+    Ok(files)
+}
+
+// File contents, `None` when the file doesn't exist
+fn read_lossy(path: &Path) -> Result<Option<String>> {
+    match path.is_file() {
+        true => std::fs::read(path).map(|b|Some(String::from_utf8_lossy(&b).into_owned())),
+        false => Ok(None),
+    }
+}
@@ -0,0 +1,193 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::ir::{Choice, VariantKind};
+use crate::parser::Context;
+
+use super::component::struct_tokens;
+use super::{ident, root_path, title_and_descr_tokens, type_path};
+
+
+/// Enum of choice definition with its trait implementations and nested components
+pub fn choice_tokens(choice: &Choice, module: &str, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let enums = enum_tokens(choice, module, ctx);
+
+    // Component is needed only for `new` of nested structs
+    let imports = match choice.variants.iter().any(|v|matches!(v.kind, VariantKind::Object(_))) {
+        true => quote!(UblError, Component, Componentable, ValidationReport),
+        false => quote!(UblError, Componentable, ValidationReport),
+    };
+
+    quote! {
+        use serde::{Deserialize, Serialize};
+        use #root::{#imports};
+
+        #enums
+    }
+}
+
+// Untagged enum followed by structs of its inline object variants
+pub fn enum_tokens(choice: &Choice, module: &str, ctx: &Context) -> TokenStream {
+    let name = ident(&choice.name);
+    let key = &choice.key;
+    let title_and_descr = match choice.title.is_some() || choice.description.is_some() {
+        true => {
+            let methods = title_and_descr_tokens(choice.title.as_ref(), choice.description.as_ref());
+            quote!(impl #name { #methods })
+        },
+        false => TokenStream::new(),
+    };
+
+    let mut variants = vec![];
+    let mut readers = vec![];
+    let mut reads = vec![];
+    let mut delegated = vec![];
+    let mut matches = vec![];
+    let mut nested = vec![];
+
+    for v in choice.variants.iter() {
+        let variant = ident(&v.name);
+
+        let ty = match &v.kind {
+            VariantKind::Text => quote!(String),
+            VariantKind::Number => quote!(serde_json::Number),
+            VariantKind::Integer => quote!(i64),
+            VariantKind::Boolean => quote!(bool),
+            VariantKind::Ref { target, .. } => type_path(target, module, ctx),
+            VariantKind::Object(c) => {
+                nested.push(struct_tokens(c, module, ctx));
+
+                let name = ident(&c.name);
+                quote!(#name)
+            },
+        };
+
+        // Branch matches when the value reads as its type, components have to be valid too
+        let is_validated = matches!(&v.kind, VariantKind::Object(_) | VariantKind::Ref { validated: true, .. });
+
+        matches.push(match is_validated {
+            true => quote!(serde_json::from_value::<#ty>(value.clone()).is_ok_and(|v|v.validate().is_ok())),
+            false => quote!(serde_json::from_value::<#ty>(value.clone()).is_ok()),
+        });
+
+        // Value that reads only as a component variant reports the violations of the component
+        reads.push(quote!(serde_json::from_value::<#ty>(value.clone()).is_ok()));
+
+        if is_validated {
+            delegated.push(quote!((1, Self::#variant(v)) => v.validate_into(pointer, report),));
+        }
+
+        // Unknown fields are removed from the variants that have fields
+        readers.push(match &v.kind {
+            VariantKind::Object(_) | VariantKind::Ref { .. } => quote! {
+                |value, pointer, removed| {
+                    <#ty as Componentable<#ty>>::remove_unknown_fields(value, pointer, removed);
+                    serde_json::from_value::<#ty>(value.clone()).is_ok()
+                }
+            },
+            _ => quote!(|value, _, _|serde_json::from_value::<#ty>(value.clone()).is_ok()),
+        });
+
+        variants.push(quote!(#variant(#ty)));
+    }
+
+    let root = root_path(ctx);
+
+    let result = match choice.exclusive {
+        true => quote! {
+            match matches {
+                1 => Ok(self),
+                0 => Err(UblError::no_match(#key)),
+                n => Err(UblError::ambiguous_match(#key, n)),
+            }
+        },
+        false => quote! {
+            match matches {
+                0 => Err(UblError::no_match(#key)),
+                _ => Ok(self),
+            }
+        },
+    };
+
+    // Branch the value was meant for is known when it reads only as one, otherwise failed match is
+    // reported for the whole value
+    let validate_into = match delegated.is_empty() {
+        true => quote! {
+            fn validate_into(&self, pointer: &str, report: &mut ValidationReport) {
+                if let Err(e) = self.validate() {
+                    report.push(pointer, e);
+                }
+            }
+        },
+        false => quote! {
+            fn validate_into(&self, pointer: &str, report: &mut ValidationReport) {
+                let e = match self.validate() {
+                    Ok(_) => return,
+                    Err(e) => e,
+                };
+
+                let reads = match serde_json::to_value(self) {
+                    Ok(value) => [
+                        #(#reads),*
+                    ].into_iter().filter(|r|*r).count(),
+                    Err(_) => 0,
+                };
+
+                match (reads, self) {
+                    #(#delegated)*
+                    _ => report.push(pointer, e),
+                }
+            }
+        },
+    };
+
+    // Enum is serialized as the value of the variant, variants are tried in schema order
+    quote! {
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        #[serde(untagged)]
+        pub enum #name {
+            #(#variants),*
+        }
+
+        impl AsMut<#name> for #name {
+            fn as_mut(&mut self) -> &mut Self {
+                self
+            }
+        }
+
+        impl Componentable<#name> for #name {
+            fn validate(&self) -> Result<&Self, UblError> {
+                let value = serde_json::to_value(self)
+                    .map_err(|e|UblError::inner_component(#key, e.to_string()))?;
+
+                let matches = [
+                    #(#matches),*
+                ].into_iter().filter(|m|*m).count();
+
+                #result
+            }
+
+            fn get(self) -> Result<Self, UblError> {
+                self.validate().map(|s|s.clone())
+            }
+
+            fn additional_props_allowed() -> bool {
+                false
+            }
+
+            #validate_into
+
+            // Value is read as the first variant it matches without unknown fields, if there's one
+            fn remove_unknown_fields(value: &mut serde_json::Value, pointer: &str, removed: &mut Vec<#root::UnknownField>) {
+                #root::remove_unknown_variant_fields(value, pointer, removed, &[
+                    #(#readers),*
+                ])
+            }
+        }
+
+        #title_and_descr
+
+        #(#nested)*
+    }
+}
@@ -0,0 +1,140 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::fs::create_dir_all;
+use std::io::Result;
+use std::path::Path;
+
+use crate::ir::CodeList;
+use crate::parser::snaker;
+
+use super::{file_writer, ident, mod_tokens};
+
+
+/// Write `code_lists` module with an enum file for each code list
+pub fn code_lists_writer(src_path: &Path, code_lists: &[CodeList]) -> Result<()> {
+    let out_dir = src_path.join("code_lists");
+    let mut mods = vec![];
+
+    create_dir_all(&out_dir)?;
+
+    for code_list in code_lists {
+        let file_name = snaker(&code_list.name);
+        file_writer(&out_dir.join(format!("{}.rs", file_name)), code_list_tokens(code_list))?;
+        mods.push(file_name);
+    }
+
+    file_writer(&out_dir.join("mod.rs"), mod_tokens(&mods))
+}
+
+// Enum with a variant for each code and `Other` for codes not in the list. It's serialized as
+// the code, so it reads any string.
+fn code_list_tokens(code_list: &CodeList) -> TokenStream {
+    let name = ident(&code_list.name);
+    let short_name = &code_list.short_name;
+    let long_name = option_tokens(code_list.long_name.as_ref());
+    let version = option_tokens(code_list.version.as_ref());
+    let canonical_uri = option_tokens(code_list.canonical_uri.as_ref());
+
+    let doc = match &code_list.long_name {
+        Some(l) => format!(" {} code list `{}`", l, short_name),
+        None => format!(" Code list `{}`", short_name),
+    };
+
+    let variants = code_list.codes.iter().map(|c|ident(&c.variant)).collect::<Vec<_>>();
+    let values = code_list.codes.iter().map(|c|&c.value).collect::<Vec<_>>();
+    let names = code_list.codes.iter().map(|c|option_tokens(c.name.as_ref()));
+    let descriptions = code_list.codes.iter().map(|c|option_tokens(c.description.as_ref()));
+    let docs = code_list.codes.iter().map(|c|match &c.name {
+        Some(n) => format!(" `{}` {}", c.value, n),
+        None => format!(" `{}`", c.value),
+    });
+
+    quote! {
+        use serde::{Deserialize, Serialize};
+
+        #[doc = #doc]
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum #name {
+            #(
+                #[doc = #docs]
+                #variants,
+            )*
+            /// Code that is not in the list
+            Other(String),
+        }
+
+        impl #name {
+            pub const SHORT_NAME: &'static str = #short_name;
+            pub const LONG_NAME: Option<&'static str> = #long_name;
+            pub const VERSION: Option<&'static str> = #version;
+            pub const CANONICAL_URI: Option<&'static str> = #canonical_uri;
+
+            /// Listed code, `None` when the code is not in the list
+            pub fn from_code(code: &str) -> Option<Self> {
+                match code {
+                    #(#values => Some(Self::#variants),)*
+                    _ => None,
+                }
+            }
+            /// Code as written in documents
+            pub fn code(&self) -> &str {
+                match self {
+                    #(Self::#variants => #values,)*
+                    Self::Other(code) => code,
+                }
+            }
+            pub fn name(&self) -> Option<&'static str> {
+                match self {
+                    #(Self::#variants => #names,)*
+                    Self::Other(_) => None,
+                }
+            }
+            pub fn description(&self) -> Option<&'static str> {
+                match self {
+                    #(Self::#variants => #descriptions,)*
+                    Self::Other(_) => None,
+                }
+            }
+            pub fn is_listed(&self) -> bool {
+                !matches!(self, Self::Other(_))
+            }
+        }
+
+        impl From<&str> for #name {
+            fn from(code: &str) -> Self {
+                Self::from_code(code).unwrap_or_else(||Self::Other(code.to_owned()))
+            }
+        }
+
+        impl From<#name> for String {
+            fn from(v: #name) -> Self {
+                v.code().to_owned()
+            }
+        }
+
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "{}", self.code())
+            }
+        }
+
+        impl Serialize for #name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+                serializer.serialize_str(self.code())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for #name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+                String::deserialize(deserializer).map(|s|Self::from(s.as_str()))
+            }
+        }
+    }
+}
+
+fn option_tokens(value: Option<&String>) -> TokenStream {
+    match value {
+        Some(v) => quote!(Some(#v)),
+        None => quote!(None),
+    }
+}
@@ -0,0 +1,1145 @@
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::quote;
+
+use crate::ir::{Array, Component, Constraints, Document, Extension, Field, FieldKind, SingleItem, ValueKind};
+use crate::parser::{enum_namer, snaker, Context};
+
+use super::choice::enum_tokens;
+use super::{ident, root_path, title_and_descr_tokens, type_path};
+
+
+const UC_ALPHABETS: [&str; 26] = [
+    "A", "B", "C", "D", "E", "F", "G", "H", "I", "J",
+    "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T",
+    "U", "V", "W", "X", "Y", "Z"
+];
+
+/// Component struct with its trait implementations, array wrapper structs and nested components
+pub fn component_tokens(comp: &Component, module: &str, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let structs = struct_tokens(comp, module, ctx);
+
+    quote! {
+        use serde::{Deserialize, Serialize};
+        use #root::{UblError, Component, Componentable, ValidationReport};
+
+        #structs
+    }
+}
+
+// Struct of the component followed by structs of its arrays and inline objects
+pub fn struct_tokens(comp: &Component, module: &str, ctx: &Context) -> TokenStream {
+    let name = ident(&comp.name);
+    let additional_props = comp.additional_props;
+
+    let fields = comp.fields.iter().map(|f|field_tokens(f, module, ctx));
+    let validations = comp.fields.iter().map(|f|validation_tokens(f, &comp.key, ctx, Failure::Return));
+    let constraints = comp.fields.iter().map(|f|constraint_tokens(f, &comp.key, Failure::Return));
+    let reports = comp.fields.iter()
+        .flat_map(|f|[
+            validation_tokens(f, &comp.key, ctx, Failure::Report),
+            constraint_tokens(f, &comp.key, Failure::Report),
+        ])
+        .filter(|t|!t.is_empty())
+        .collect::<Vec<TokenStream>>();
+    let report_args = report_args_tokens(reports.is_empty());
+    let props_field = props_field_tokens(additional_props);
+    let unknown_fields = unknown_fields_tokens(comp, module, ctx);
+    let (setters, chained) = setter_tokens(comp, module, ctx);
+    let builder = match ctx.typestate_builders {
+        true => typestate_builder_tokens(comp, module, ctx),
+        false => TokenStream::new(),
+    };
+    let title_and_descr = title_and_descr_tokens(comp.title.as_ref(), comp.description.as_ref());
+    let new_instance = new_instance_tokens(comp, module, ctx);
+    let value_conversions = value_conversion_tokens(comp, ctx);
+
+    let nested = comp.fields.iter()
+        .filter_map(|f|match &f.kind {
+            FieldKind::Array(a) if a.single.is_some() => None,
+            FieldKind::Array(a) => Some(array_tokens(a, module, ctx)),
+            FieldKind::Object(c) => Some(struct_tokens(c, module, ctx)),
+            FieldKind::Choice(c) => Some(enum_tokens(c, module, ctx)),
+            _ => None,
+        });
+
+    // Properties not in the schema fail deserialization unless they're allowed
+    let deny_unknown = match additional_props {
+        true => TokenStream::new(),
+        false => quote!(#[serde(deny_unknown_fields)]),
+    };
+
+    quote! {
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        #deny_unknown
+        pub struct #name {
+            #(#fields)*
+            #props_field
+        }
+
+        impl AsMut<#name> for #name {
+            fn as_mut(&mut self) -> &mut Self {
+                self
+            }
+        }
+
+        impl Componentable<#name> for #name {
+            fn validate(&self) -> Result<&Self, UblError> {
+                #(#validations)*
+                #(#constraints)*
+
+                Ok(self)
+            }
+
+            fn get(self) -> Result<Self, UblError> {
+                self.validate().map(|s|s.clone())
+            }
+
+            fn additional_props_allowed() -> bool {
+                #additional_props
+            }
+
+            fn validate_into(&self, #report_args) {
+                #(#reports)*
+            }
+
+            #unknown_fields
+        }
+
+        impl #name {
+            #title_and_descr
+            #new_instance
+            #setters
+        }
+
+        #chained
+
+        #value_conversions
+
+        #builder
+
+        #(#nested)*
+    }
+}
+
+/// Document trait implementation and envelope type alias for document root
+pub fn document_tokens(doc: &Document, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let root_name = ident(&doc.root);
+    let envelope = ident(format!("{}Envelope", doc.name));
+    let namespace = format!("urn:oasis:names:specification:ubl:schema:xsd:{}-2", doc.name);
+    let doc_comment = format!(" {} document with namespace declarations", doc.name);
+
+    quote! {
+        impl #root::Document for #root_name {
+            const NAMESPACE: &'static str = #namespace;
+        }
+
+        #[doc = #doc_comment]
+        pub type #envelope = #root::Envelope<#root_name>;
+    }
+}
+
+/// Empty structs for extension definitions, there's nothing in them to validate
+pub fn extension_tokens(extensions: &[Extension], ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let names = extensions.iter().map(|e|ident(&e.name));
+
+    quote! {
+        use serde::{Deserialize, Serialize};
+        use #root::{UblError, Componentable, ValidationReport};
+
+        #(
+            #[derive(Debug, Clone, Deserialize, Serialize)]
+            #[serde(rename_all = "PascalCase")]
+            pub struct #names {}
+
+            impl AsMut<#names> for #names {
+                fn as_mut(&mut self) -> &mut Self {
+                    self
+                }
+            }
+
+            impl Componentable<#names> for #names {
+                fn validate(&self) -> Result<&Self, UblError> {
+                    Ok(self)
+                }
+
+                fn get(self) -> Result<Self, UblError> {
+                    Ok(self)
+                }
+
+                fn additional_props_allowed() -> bool {
+                    false
+                }
+
+                fn validate_into(&self, _pointer: &str, _report: &mut ValidationReport) {}
+            }
+        )*
+    }
+}
+
+// Properties not in the schema are kept and written back when they're allowed
+fn props_field_tokens(additional_props: bool) -> TokenStream {
+    match additional_props {
+        true => quote! {
+            /// Properties not in the schema
+            #[serde(flatten)]
+            pub additional_props: serde_json::Map<String, serde_json::Value>,
+        },
+        false => TokenStream::new(),
+    }
+}
+
+// Lenient reading removes properties not in the schema unless they're allowed, and then the ones
+// in the components of the fields. Nothing is generated when there's nothing to remove.
+fn unknown_fields_tokens(comp: &Component, module: &str, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+
+    let unlisted = match comp.additional_props {
+        true => TokenStream::new(),
+        false => {
+            let keys = comp.fields.iter().map(|f|&f.key);
+            quote!(#root::remove_unlisted_fields(value, &[#(#keys),*], pointer, removed);)
+        },
+    };
+
+    let fields = comp.fields.iter()
+        .filter_map(|f|{
+            let key = &f.key;
+            let pointer_key = pointer_key(key);
+
+            // Single items are read from JSON arrays like the wrapped ones
+            let remove = match (&f.kind, single_item(f)) {
+                (FieldKind::Array(a), Some(_)) => {
+                    let item = type_path(&a.item, module, ctx);
+                    quote!(#root::remove_unknown_item_fields::<#item>)
+                },
+                (FieldKind::Array(_) | FieldKind::Object(_) | FieldKind::Choice(_), _) => {
+                    let ty = field_type_tokens(f, module, ctx);
+                    quote!(<#ty as Componentable<#ty>>::remove_unknown_fields)
+                },
+                _ => return None,
+            };
+
+            Some(quote! {
+                if let Some(v) = value.get_mut(#key) {
+                    #remove(v, &format!("{}/{}", pointer, #pointer_key), removed);
+                }
+            })
+        })
+        .collect::<Vec<TokenStream>>();
+
+    if unlisted.is_empty() && fields.is_empty() {
+        return TokenStream::new()
+    }
+
+    quote! {
+        fn remove_unknown_fields(value: &mut serde_json::Value, pointer: &str, removed: &mut Vec<#root::UnknownField>) {
+            #unlisted
+            #(#fields)*
+        }
+    }
+}
+
+// Array property generated as its item
+fn single_item(field: &Field) -> Option<(&Array, SingleItem)> {
+    match &field.kind {
+        FieldKind::Array(a) => a.single.map(|s|(a, s)),
+        _ => None,
+    }
+}
+
+fn field_type_tokens(field: &Field, module: &str, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+
+    if let Some((a, single)) = single_item(field) {
+        let item = type_path(&a.item, module, ctx);
+
+        return match single {
+            SingleItem::Item => item,
+            SingleItem::Boxed => quote!(Box<#item>),
+        }
+    }
+
+    match &field.kind {
+        FieldKind::Text => quote!(String),
+        FieldKind::Formatted(_) => quote!(#root::FormattedValue),
+        FieldKind::Temporal(t) => {
+            let name = ident(t.name());
+            quote!(#root::#name)
+        },
+        FieldKind::Number => quote!(serde_json::Number),
+        FieldKind::Integer => quote!(i64),
+        FieldKind::Boolean => quote!(bool),
+        FieldKind::Array(a) => {
+            let name = ident(&a.name);
+            quote!(#name)
+        },
+        FieldKind::Object(c) => {
+            let name = ident(&c.name);
+            quote!(#name)
+        },
+        FieldKind::Choice(c) => {
+            let name = ident(&c.name);
+            quote!(#name)
+        },
+    }
+}
+
+fn field_tokens(field: &Field, module: &str, ctx: &Context) -> TokenStream {
+    // Fields have to be serialized as they are in json spec
+    let key = &field.key;
+    let name = ident(&field.name);
+    let ty = field_type_tokens(field, module, ctx);
+
+    // Single items are still written as arrays
+    if single_item(field).is_some() {
+        let root = root_path(ctx);
+
+        return match field.required {
+            true => {
+                let with_path = quote!(#root::single_item).to_string().replace(' ', "");
+
+                quote! {
+                    #[serde(rename = #key)]
+                    #[serde(with = #with_path)]
+                    pub #name: #ty,
+                }
+            },
+            false => {
+                let with_path = quote!(#root::optional_single_item).to_string().replace(' ', "");
+
+                quote! {
+                    #[serde(rename = #key)]
+                    #[serde(default)]
+                    #[serde(with = #with_path)]
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    pub #name: Option<#ty>,
+                }
+            },
+        }
+    }
+
+    // Formatted values are read as the variant of the field format
+    if let FieldKind::Formatted(f) = &field.kind {
+        let root = root_path(ctx);
+        let de_fn = ident(match field.required {
+            true => format!("deserialize_{}", snaker(f)),
+            false => format!("deserialize_optional_{}", snaker(f)),
+        });
+        let de_path = quote!(#root::FormattedValue::#de_fn).to_string().replace(' ', "");
+
+        return match field.required {
+            true => quote! {
+                #[serde(rename = #key)]
+                #[serde(deserialize_with = #de_path)]
+                pub #name: #ty,
+            },
+            false => quote! {
+                #[serde(rename = #key)]
+                #[serde(default)]
+                #[serde(deserialize_with = #de_path)]
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub #name: Option<#ty>,
+            },
+        }
+    }
+
+    match field.required {
+        true => quote! {
+            #[serde(rename = #key)]
+            pub #name: #ty,
+        },
+        false => quote! {
+            #[serde(rename = #key)]
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub #name: Option<#ty>,
+        },
+    }
+}
+
+// Failed check either returns the error from `validate` or adds it to the report in `validate_into`
+#[derive(Clone, Copy)]
+enum Failure {
+    Return,
+    Report,
+}
+
+// Statement handling error of failed check, reported errors point to the field with `key`
+fn failure_tokens(failure: Failure, key: &str, err: TokenStream) -> TokenStream {
+    match failure {
+        Failure::Return => quote!(return Err(#err)),
+        Failure::Report => {
+            let key = pointer_key(key);
+            quote!(report.push(format!("{}/{}", pointer, #key), #err))
+        },
+    }
+}
+
+// Key escaped for JSON pointer
+fn pointer_key(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+// Arguments of `validate_into`, underscored when the body doesn't check anything
+fn report_args_tokens(unused: bool) -> TokenStream {
+    match unused {
+        true => quote!(_pointer: &str, _report: &mut ValidationReport),
+        false => quote!(pointer: &str, report: &mut ValidationReport),
+    }
+}
+
+fn validation_tokens(field: &Field, comp_key: &str, ctx: &Context, failure: Failure) -> TokenStream {
+    let root = root_path(ctx);
+    let name = ident(&field.name);
+    let path = format!("{}.{}", comp_key, field.name);
+    let fail = |err: TokenStream|failure_tokens(failure, &field.key, err);
+
+    match (&field.kind, field.required) {
+        (FieldKind::Text, true) => {
+            let empty = fail(quote!(UblError::empty(#path)));
+
+            quote! {
+                if self.#name.is_empty() {
+                    #empty
+                }
+            }
+        },
+        (FieldKind::Text, false) => {
+            let empty = fail(quote!(UblError::optional_empty(#path)));
+
+            quote! {
+                if let Some(v) = &self.#name {
+                    if v.is_empty() {
+                        #empty
+                    }
+                }
+            }
+        },
+        (FieldKind::Formatted(f), true) => {
+            let variant = ident(f);
+            let empty = fail(quote!(UblError::empty(#path)));
+            let format = fail(quote!(UblError::format(#path, e)));
+
+            quote! {
+                match &self.#name {
+                    #root::FormattedValue::#variant(s) => if s.is_empty() {
+                        #empty
+                    },
+                    e => #format,
+                }
+            }
+        },
+        (FieldKind::Formatted(f), false) => {
+            let variant = ident(f);
+            let empty = fail(quote!(UblError::optional_empty(#path)));
+            let format = fail(quote!(UblError::optional_format(#path, e)));
+
+            quote! {
+                if let Some(v) = &self.#name {
+                    match v {
+                        #root::FormattedValue::#variant(s) => if s.is_empty() {
+                            #empty
+                        },
+                        e => #format,
+                    }
+                }
+            }
+        },
+        // Reported components check everything in them and report under their own pointer
+        (FieldKind::Array(_) | FieldKind::Object(_) | FieldKind::Choice(_), required) => {
+            // Single item is at index 0 of the array in JSON
+            let key = match single_item(field) {
+                Some(_) => format!("{}/0", pointer_key(&field.key)),
+                None => pointer_key(&field.key),
+            };
+
+            match (failure, required) {
+                (Failure::Return, true) => quote! {
+                    if let Err(e) = self.#name.validate() {
+                        return Err(UblError::component(#path, e));
+                    }
+                },
+                (Failure::Return, false) => quote! {
+                    if let Some(v) = &self.#name {
+                        if let Err(e) = v.validate() {
+                            return Err(UblError::optional_component(#path, e));
+                        }
+                    }
+                },
+                (Failure::Report, true) => quote! {
+                    self.#name.validate_into(&format!("{}/{}", pointer, #key), report);
+                },
+                (Failure::Report, false) => quote! {
+                    if let Some(v) = &self.#name {
+                        v.validate_into(&format!("{}/{}", pointer, #key), report);
+                    }
+                },
+            }
+        },
+        // Dates and times are checked when they're parsed
+        (FieldKind::Number | FieldKind::Integer | FieldKind::Boolean | FieldKind::Temporal(_), _) => quote!(),
+    }
+}
+
+// Checks for allowed values, pattern, length limits and numeric bounds of the field value
+fn constraint_tokens(field: &Field, comp_key: &str, failure: Failure) -> TokenStream {
+    let name = ident(&field.name);
+    let path = format!("{}.{}", comp_key, field.name);
+    let fail = |err: TokenStream|failure_tokens(failure, &field.key, err);
+
+    let checks = match &field.kind {
+        FieldKind::Text | FieldKind::Formatted(_) | FieldKind::Temporal(_) => string_check_tokens(&field.constraints, &path, &fail),
+        FieldKind::Number | FieldKind::Integer => number_check_tokens(&field.constraints, &path, &fail),
+        _ => TokenStream::new(),
+    };
+
+    if checks.is_empty() {
+        return checks
+    }
+
+    // Checks are made against `v` which is &str for strings and f64 for numbers and integers
+    match (&field.kind, field.required) {
+        (FieldKind::Text, true) => quote! {
+            {
+                let v = self.#name.as_str();
+                #checks
+            }
+        },
+        (FieldKind::Text, false) => quote! {
+            if let Some(v) = &self.#name {
+                let v = v.as_str();
+                #checks
+            }
+        },
+        (FieldKind::Formatted(_) | FieldKind::Temporal(_), true) => quote! {
+            {
+                let v = self.#name.to_string();
+                let v = v.as_str();
+                #checks
+            }
+        },
+        (FieldKind::Formatted(_) | FieldKind::Temporal(_), false) => quote! {
+            if let Some(v) = &self.#name {
+                let v = v.to_string();
+                let v = v.as_str();
+                #checks
+            }
+        },
+        (FieldKind::Number, true) => quote! {
+            if let Some(v) = self.#name.as_f64() {
+                #checks
+            }
+        },
+        (FieldKind::Number, false) => quote! {
+            if let Some(v) = self.#name.as_ref().and_then(|n|n.as_f64()) {
+                #checks
+            }
+        },
+        (FieldKind::Integer, true) => quote! {
+            {
+                let v = self.#name as f64;
+                #checks
+            }
+        },
+        (FieldKind::Integer, false) => quote! {
+            if let Some(v) = self.#name.map(|n|n as f64) {
+                #checks
+            }
+        },
+        _ => TokenStream::new(),
+    }
+}
+
+fn string_check_tokens(constraints: &Constraints, path: &str, fail: &dyn Fn(TokenStream) -> TokenStream) -> TokenStream {
+    let allowed = constraints.allowed.as_ref().map(|a|{
+        let not_allowed = fail(quote!(UblError::not_allowed(#path, v, &[#(#a),*])));
+
+        quote! {
+            if ![#(#a),*].contains(&v) {
+                #not_allowed
+            }
+        }
+    });
+
+    // Pattern is compiled once, builder has made sure it's valid
+    let pattern = constraints.pattern.as_ref().map(|p|{
+        let mismatch = fail(quote!(UblError::pattern_mismatch(#path, v, #p)));
+
+        quote! {
+            {
+                static PATTERN: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+                let re = PATTERN.get_or_init(||regex::Regex::new(#p).expect("Pattern is not valid"));
+
+                if !re.is_match(v) {
+                    #mismatch
+                }
+            }
+        }
+    });
+
+    let len = match constraints.min_length.is_some() || constraints.max_length.is_some() {
+        true => quote!(let len = v.chars().count();),
+        false => TokenStream::new(),
+    };
+
+    let min_length = constraints.min_length.map(|u|{
+        let u = Literal::u64_unsuffixed(u);
+        let too_short = fail(quote!(UblError::too_short(#path, len, #u)));
+
+        quote! {
+            if len < #u {
+                #too_short
+            }
+        }
+    });
+
+    let max_length = constraints.max_length.map(|u|{
+        let u = Literal::u64_unsuffixed(u);
+        let too_long = fail(quote!(UblError::too_long(#path, len, #u)));
+
+        quote! {
+            if len > #u {
+                #too_long
+            }
+        }
+    });
+
+    quote!(#allowed #pattern #len #min_length #max_length)
+}
+
+fn number_check_tokens(constraints: &Constraints, path: &str, fail: &dyn Fn(TokenStream) -> TokenStream) -> TokenStream {
+    let minimum = constraints.minimum.map(|n|{
+        let n = Literal::f64_unsuffixed(n);
+        let below = fail(quote!(UblError::below_minimum(#path, v, #n)));
+
+        quote! {
+            if v < #n {
+                #below
+            }
+        }
+    });
+
+    let maximum = constraints.maximum.map(|n|{
+        let n = Literal::f64_unsuffixed(n);
+        let above = fail(quote!(UblError::above_maximum(#path, v, #n)));
+
+        quote! {
+            if v > #n {
+                #above
+            }
+        }
+    });
+
+    quote!(#minimum #maximum)
+}
+
+// New instance method takes required fields as arguments and inits optional fields as None.
+// Required strings are generic so they can come in as references.
+fn new_instance_tokens(comp: &Component, module: &str, ctx: &Context) -> TokenStream {
+    let mut generics: Vec<Ident> = vec![];
+    let mut args = vec![];
+    let mut inits = vec![];
+
+    for field in comp.fields.iter() {
+        let name = ident(&field.name);
+
+        if !field.required {
+            inits.push(quote!(#name: None));
+            continue;
+        }
+
+        match (&field.kind, UC_ALPHABETS.get(generics.len())) {
+            (FieldKind::Text, Some(a)) => {
+                let t = ident(a);
+
+                args.push(quote!(#name: #t));
+                inits.push(quote!(#name: #name.into()));
+                generics.push(t);
+            },
+            (FieldKind::Array(a @ Array { single: Some(SingleItem::Boxed), .. }), _) => {
+                let ty = type_path(&a.item, module, ctx);
+
+                args.push(quote!(#name: #ty));
+                inits.push(quote!(#name: Box::new(#name)));
+            },
+            _ => {
+                let ty = field_type_tokens(field, module, ctx);
+
+                args.push(quote!(#name: #ty));
+                inits.push(quote!(#name));
+            },
+        }
+    }
+
+    if comp.additional_props {
+        inits.push(quote!(additional_props: serde_json::Map::new()));
+    }
+
+    let (generics, bounds) = match generics.is_empty() {
+        true => (quote!(), quote!()),
+        false => (quote!(<#(#generics),*>), quote!(where #(#generics: Into<String>),*)),
+    };
+
+    quote! {
+        pub fn new #generics (#(#args),*) -> Component<Self> #bounds {
+            Component(Self {
+                #(#inits),*
+            })
+        }
+    }
+}
+
+// Data types holding single value convert from the bare value, e.g. `TextType` from `&str` and
+// `AmountType` from number and currency. Values are created with `new` so optional fields are None.
+fn value_conversion_tokens(comp: &Component, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let name = ident(&comp.name);
+    let path = format!("{}._uc", comp.name);
+
+    let kind = match comp.value_kind() {
+        Some(k) => k,
+        None => return TokenStream::new(),
+    };
+
+    match kind {
+        ValueKind::Text => quote! {
+            impl From<&str> for #name {
+                fn from(v: &str) -> Self {
+                    Self::new(v).0
+                }
+            }
+
+            impl From<String> for #name {
+                fn from(v: String) -> Self {
+                    Self::new(v).0
+                }
+            }
+        },
+        ValueKind::Temporal(t) => {
+            let temporal = ident(t.name());
+
+            quote! {
+                impl From<#root::#temporal> for #name {
+                    fn from(v: #root::#temporal) -> Self {
+                        Self::new(v).0
+                    }
+                }
+
+                impl std::convert::TryFrom<&str> for #name {
+                    type Error = UblError;
+
+                    fn try_from(v: &str) -> Result<Self, UblError> {
+                        #root::#temporal::parse(v).map(Self::from)
+                    }
+                }
+            }
+        },
+        ValueKind::Number { unit: None } => quote! {
+            impl From<serde_json::Number> for #name {
+                fn from(v: serde_json::Number) -> Self {
+                    Self::new(v).0
+                }
+            }
+
+            impl From<i64> for #name {
+                fn from(v: i64) -> Self {
+                    Self::new(v.into()).0
+                }
+            }
+
+            impl std::convert::TryFrom<f64> for #name {
+                type Error = UblError;
+
+                fn try_from(v: f64) -> Result<Self, UblError> {
+                    serde_json::Number::from_f64(v)
+                        .map(Self::from)
+                        .ok_or_else(||UblError::not_finite(#path, v))
+                }
+            }
+        },
+        ValueKind::Number { unit: Some(unit) } => {
+            // Required unit is an argument of `new`, optional one is set after it
+            let new_instance = match comp.fields.iter().find(|f|f.name == unit).map(|f|f.required) {
+                Some(true) => {
+                    let args = comp.fields.iter()
+                        .filter(|f|f.required)
+                        .map(|f|match f.key == "_" {
+                            true => quote!(v),
+                            false => quote!(unit),
+                        });
+
+                    quote!(Self::new(#(#args),*).0)
+                },
+                _ => {
+                    let with = ident(format!("with_{}", unit.trim_start_matches('_')));
+                    quote!(Self::new(v).#with(unit).0)
+                },
+            };
+
+            quote! {
+                impl<U> From<(serde_json::Number, U)> for #name where U: Into<String> {
+                    fn from((v, unit): (serde_json::Number, U)) -> Self {
+                        #new_instance
+                    }
+                }
+
+                impl<U> From<(i64, U)> for #name where U: Into<String> {
+                    fn from((v, unit): (i64, U)) -> Self {
+                        Self::from((serde_json::Number::from(v), unit))
+                    }
+                }
+
+                impl<U> std::convert::TryFrom<(f64, U)> for #name where U: Into<String> {
+                    type Error = UblError;
+
+                    fn try_from((v, unit): (f64, U)) -> Result<Self, UblError> {
+                        serde_json::Number::from_f64(v)
+                            .map(|n|Self::from((n, unit)))
+                            .ok_or_else(||UblError::not_finite(#path, v))
+                    }
+                }
+            }
+        },
+    }
+}
+
+// Generics, argument type, bounds and the stored value of setter argument `v`. Strings can come in
+// as references and boxed items are boxed by the setter.
+fn setter_arg_tokens(field: &Field, module: &str, ctx: &Context) -> (TokenStream, TokenStream, TokenStream, TokenStream) {
+    match &field.kind {
+        FieldKind::Text => (quote!(<T>), quote!(T), quote!(where T: Into<String>), quote!(v.into())),
+        FieldKind::Array(a @ Array { single: Some(SingleItem::Boxed), .. }) => {
+            (quote!(), type_path(&a.item, module, ctx), quote!(), quote!(Box::new(v)))
+        },
+        _ => (quote!(), field_type_tokens(field, module, ctx), quote!(), quote!(v)),
+    }
+}
+
+// Setters of the struct and chained versions of them for `Component` returned by `new`, so that
+// component can be built in one chain. Arrays get push methods creating the array on first item.
+fn setter_tokens(comp: &Component, module: &str, ctx: &Context) -> (TokenStream, TokenStream) {
+    let name = ident(&comp.name);
+    let mut setters = vec![];
+    let mut chained = vec![];
+
+    for field in comp.fields.iter() {
+        let field_name = ident(&field.name);
+        let method = field.name.trim_start_matches('_');
+        let set = ident(format!("set_{}", method));
+        let with = ident(format!("with_{}", method));
+
+        let (generics, arg, bounds, value) = setter_arg_tokens(field, module, ctx);
+
+        let value = match field.required {
+            true => value,
+            false => quote!(Some(#value)),
+        };
+
+        setters.push(quote! {
+            pub fn #set #generics (&mut self, v: #arg) -> &mut Self #bounds {
+                self.#field_name = #value;
+                self
+            }
+        });
+
+        chained.push(quote! {
+            pub fn #with #generics (mut self, v: #arg) -> Self #bounds {
+                self.0.#set(v);
+                self
+            }
+        });
+
+        if let FieldKind::Array(a @ Array { single: None, .. }) = &field.kind {
+            let push = ident(format!("push_{}", method));
+            let array = ident(&a.name);
+            let item = type_path(&a.item, module, ctx);
+
+            let array_ref = match field.required {
+                true => quote!(self.#field_name),
+                false => quote!(self.#field_name.get_or_insert_with(||#array { items: vec![] })),
+            };
+
+            setters.push(quote! {
+                /// Add item to the array, array is created when it's not there yet
+                pub fn #push(&mut self, item: #item) -> &mut Self {
+                    #array_ref.push(item);
+                    self
+                }
+            });
+
+            chained.push(quote! {
+                pub fn #push(mut self, item: #item) -> Self {
+                    self.0.#push(item);
+                    self
+                }
+            });
+        }
+    }
+
+    let chained = match chained.is_empty() {
+        true => TokenStream::new(),
+        false => quote! {
+            impl Component<#name> {
+                #(#chained)*
+            }
+        },
+    };
+
+    (quote!(#(#setters)*), chained)
+}
+
+// Builder taking fields by name. Each required field has a type parameter that stays `Missing`
+// until the field is set and holds the value in `Set` after that, so `build` is implemented only
+// for builder with every required field set.
+fn typestate_builder_tokens(comp: &Component, module: &str, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let name = ident(&comp.name);
+    let builder = ident(format!("{}Builder", comp.name));
+    let doc = format!(" Builder of [`{}`], `build` is available when every required field is set", comp.name);
+
+    let (required, optional): (Vec<&Field>, Vec<&Field>) = comp.fields.iter().partition(|f|f.required);
+
+    let states = required.iter().map(|f|ident(format!("{}State", enum_namer(&f.name)))).collect::<Vec<Ident>>();
+    let req_names = required.iter().map(|f|ident(&f.name)).collect::<Vec<Ident>>();
+    let req_types = required.iter().map(|f|field_type_tokens(f, module, ctx)).collect::<Vec<TokenStream>>();
+    let opt_names = optional.iter().map(|f|ident(&f.name)).collect::<Vec<Ident>>();
+    let opt_types = optional.iter().map(|f|field_type_tokens(f, module, ctx));
+
+    let props_field = match comp.additional_props {
+        true => quote!(additional_props: serde_json::Map::new(),),
+        false => TokenStream::new(),
+    };
+
+    let mut setters = vec![];
+
+    for (i, field) in required.iter().enumerate() {
+        let method = builder_setter_ident(field);
+        let field_name = &req_names[i];
+        let ty = &req_types[i];
+
+        let (generics, arg, bounds, value) = setter_arg_tokens(field, module, ctx);
+
+        // Setting the field changes its state, others stay as they are
+        let next_states = states.iter().enumerate().map(|(j, s)|match i == j {
+            true => quote!(#root::Set<#ty>),
+            false => quote!(#s),
+        });
+
+        let moved = req_names.iter().map(|n|match n.eq(field_name) {
+            true => quote!(#n: #root::Set(#value)),
+            false => quote!(#n: self.#n),
+        });
+
+        setters.push(quote! {
+            pub fn #method #generics (self, v: #arg) -> #builder<#(#next_states),*> #bounds {
+                #builder {
+                    #(#moved,)*
+                    #(#opt_names: self.#opt_names,)*
+                }
+            }
+        });
+    }
+
+    for field in optional.iter() {
+        let method = builder_setter_ident(field);
+        let field_name = ident(&field.name);
+
+        let (generics, arg, bounds, value) = setter_arg_tokens(field, module, ctx);
+
+        setters.push(quote! {
+            pub fn #method #generics (mut self, v: #arg) -> Self #bounds {
+                self.#field_name = Some(#value);
+                self
+            }
+        });
+    }
+
+    let (params, args, set_args) = match states.is_empty() {
+        true => (quote!(), quote!(), quote!()),
+        false => (
+            quote!(<#(#states = #root::Missing),*>),
+            quote!(<#(#states),*>),
+            quote!(<#(#root::Set<#req_types>),*>),
+        ),
+    };
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone)]
+        pub struct #builder #params {
+            #(#req_names: #states,)*
+            #(#opt_names: Option<#opt_types>,)*
+        }
+
+        impl #name {
+            pub fn builder() -> #builder {
+                #builder {
+                    #(#req_names: #root::Missing,)*
+                    #(#opt_names: None,)*
+                }
+            }
+        }
+
+        impl #args #builder #args {
+            #(#setters)*
+        }
+
+        impl #builder #set_args {
+            pub fn build(self) -> Component<#name> {
+                Component(#name {
+                    #(#req_names: self.#req_names.0,)*
+                    #(#opt_names: self.#opt_names,)*
+                    #props_field
+                })
+            }
+        }
+    }
+}
+
+// Builder setter is named after the field, field `build` gets suffix to keep it apart from `build()`
+fn builder_setter_ident(field: &Field) -> Ident {
+    match field.name.trim_start_matches('_') {
+        "build" => ident("build_"),
+        name => ident(name),
+    }
+}
+
+/// Wrapper struct holding the items of array property
+fn array_tokens(array: &Array, module: &str, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let name = ident(&array.name);
+    let item = type_path(&array.item, module, ctx);
+    let struct_name = &array.name;
+
+    let len = match array.max_items.is_some() || array.min_items.is_some() {
+        true => quote!(let len = self.items.len();),
+        false => quote!(),
+    };
+
+    let max_items = |failure: Failure|array.max_items.map(|u|{
+        let u = Literal::u64_unsuffixed(u);
+        let err = quote!(UblError::too_many_items(#struct_name, len, #u));
+        let fail = match failure {
+            Failure::Return => quote!(return Err(#err)),
+            Failure::Report => quote!(report.push(pointer, #err)),
+        };
+
+        quote! {
+            if len > #u {
+                #fail
+            }
+        }
+    });
+
+    let min_items = |failure: Failure|array.min_items.map(|u|{
+        let u = Literal::u64_unsuffixed(u);
+        let err = quote!(UblError::too_few_items(#struct_name, len, #u));
+        let fail = match failure {
+            Failure::Return => quote!(return Err(#err)),
+            Failure::Report => quote!(report.push(pointer, #err)),
+        };
+
+        quote! {
+            if len < #u {
+                #fail
+            }
+        }
+    });
+
+    let (max_returned, min_returned) = (max_items(Failure::Return), min_items(Failure::Return));
+    let (max_reported, min_reported) = (max_items(Failure::Report), min_items(Failure::Report));
+
+    // Items should not be displayed on the serialized json
+    quote! {
+        #[derive(Debug, Clone, Deserialize, Serialize)]
+        #[serde(transparent)]
+        pub struct #name {
+            pub items: Vec<#item>,
+        }
+
+        impl AsMut<#name> for #name {
+            fn as_mut(&mut self) -> &mut Self {
+                self
+            }
+        }
+
+        impl Componentable<#name> for #name {
+            // Every item is validated, error tells the index of the first failed one
+            fn validate(&self) -> Result<&Self, UblError> {
+                #len
+                #max_returned
+                #min_returned
+
+                for (i, item) in self.items.iter().enumerate() {
+                    if let Err(e) = item.validate() {
+                        return Err(UblError::item(#struct_name, i, e))
+                    }
+                }
+
+                Ok(self)
+            }
+
+            fn get(self) -> Result<Self, UblError> {
+                self.validate().map(|s|s.clone())
+            }
+
+            fn additional_props_allowed() -> bool {
+                false
+            }
+
+            // Items are reported under their index in the array
+            fn validate_into(&self, pointer: &str, report: &mut ValidationReport) {
+                #len
+                #max_reported
+                #min_reported
+
+                for (i, item) in self.items.iter().enumerate() {
+                    item.validate_into(&format!("{}/{}", pointer, i), report);
+                }
+            }
+
+            fn remove_unknown_fields(value: &mut serde_json::Value, pointer: &str, removed: &mut Vec<#root::UnknownField>) {
+                #root::remove_unknown_item_fields::<#item>(value, pointer, removed)
+            }
+        }
+
+        impl #name {
+            /// Initialize new struct with one item
+            pub fn new(item: #item) -> Component<Self> {
+                Component(Self {
+                    items: vec![item],
+                })
+            }
+            /// Push new item to existing struct
+            pub fn push(&mut self, item: #item) {
+                self.items.push(item);
+            }
+            /// Iterate mutably over added items
+            pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, #item> {
+                self.items.iter_mut()
+            }
+            /// Iterate over added items
+            pub fn iter(&self) -> std::slice::Iter<'_, #item> {
+                self.items.iter()
+            }
+        }
+
+        impl From<#item> for #name {
+            fn from(item: #item) -> Self {
+                Self {
+                    items: vec![item],
+                }
+            }
+        }
+
+        impl From<Vec<#item>> for #name {
+            fn from(items: Vec<#item>) -> Self {
+                Self {
+                    items,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1222 @@
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::quote;
+use std::fs::File;
+use std::io::{Result, Write};
+use std::path::Path;
+
+use crate::ir::{Library, Temporal};
+use crate::parser::{snaker, Context};
+
+use super::{ident, source};
+
+
+// Namespace declarations of the common libraries in UBL JSON documents
+const NAMESPACES: [(&str, &str); 4] = [
+    ("_A", "urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2"),
+    ("_B", "urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2"),
+    ("_E", "urn:oasis:names:specification:ubl:schema:xsd:CommonExtensionComponents-2"),
+    ("_S", "urn:oasis:names:specification:ubl:schema:xsd:CommonSignatureComponents-2"),
+];
+
+/// Write `lib.rs` declaring the modules and runtime shared by them
+pub fn lib_writer(src_path: &Path, library: &Library, ctx: &Context, exporter: bool) -> Result<()> {
+    // Create lib.rs file in src directory
+    let lib_path = src_path.join("lib.rs");
+    let mut mod_file = File::create(&lib_path)?;
+
+    let exporter = exporter.then(||quote!(pub mod exporter;));
+    let code_lists = (!library.code_lists.is_empty()).then(||quote!(pub mod code_lists;));
+    let mods = library.modules.iter().map(|m|ident(&m.name));
+
+    write!(mod_file, "{}", source(quote! {
+        #exporter
+        #code_lists
+        #(pub mod #mods;)*
+
+        use thiserror::Error;
+        use serde::{Deserialize, Serialize};
+        use std::fmt::{Display, Formatter, Result as FmtResult};
+    }, &lib_path)?)?;
+
+    // Add trait and wrapper struct for created components + Error handling
+    write!(mod_file, "\n\n{}", source(componentable_tokens(), &lib_path)?)?;
+    write!(mod_file, "\n\n{}", source(error_tokens(), &lib_path)?)?;
+
+    // Add report collecting all validation failures
+    write!(mod_file, "\n\n{}", source(quote! {
+        /// Failed check found by `validate_all`
+        #[derive(Debug)]
+        pub struct Violation {
+            /// JSON pointer of the value, e.g. `/Invoice/0/InvoiceLine/3/Price/0/PriceAmount/0`
+            pub pointer: String,
+            pub error: UblError,
+        }
+
+        impl Violation {
+            pub fn code(&self) -> &'static str {
+                self.error.code()
+            }
+        }
+
+        impl Serialize for Violation {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+                use serde::ser::SerializeStruct;
+
+                let mut state = serializer.serialize_struct("Violation", 3)?;
+                state.serialize_field("pointer", &self.pointer)?;
+                state.serialize_field("code", self.code())?;
+                state.serialize_field("message", &self.error.to_string())?;
+                state.end()
+            }
+        }
+
+        /// Every failed check of validated value, empty when the value is valid
+        #[derive(Debug, Default, Serialize)]
+        #[serde(transparent)]
+        pub struct ValidationReport {
+            pub violations: Vec<Violation>,
+        }
+
+        impl ValidationReport {
+            pub fn push<P>(&mut self, pointer: P, error: UblError) where P: Into<String> {
+                self.violations.push(Violation { pointer: pointer.into(), error });
+            }
+            pub fn is_empty(&self) -> bool {
+                self.violations.is_empty()
+            }
+            pub fn len(&self) -> usize {
+                self.violations.len()
+            }
+            pub fn iter(&self) -> std::slice::Iter<'_, Violation> {
+                self.violations.iter()
+            }
+            /// `Ok` when nothing failed, the report otherwise
+            pub fn into_result(self) -> Result<(), Self> {
+                match self.is_empty() {
+                    true => Ok(()),
+                    false => Err(self),
+                }
+            }
+        }
+
+        impl Display for ValidationReport {
+            fn fmt(&self, f: &mut Formatter) -> FmtResult {
+                for v in self.violations.iter() {
+                    writeln!(f, "{} [{}]: {}", v.pointer, v.code(), v.error)?;
+                }
+
+                Ok(())
+            }
+        }
+
+        impl std::error::Error for ValidationReport {}
+    }, &lib_path)?)?;
+
+    // Add namespace declarations and envelope for documents
+    write!(mod_file, "\n\n{}", source(envelope_tokens(), &lib_path)?)?;
+
+    // Add date and time types, they're written and read in XML Schema formats
+    write!(mod_file, "\n\n{}", source(time_offset_tokens(), &lib_path)?)?;
+
+    for temporal in Temporal::ALL {
+        write!(mod_file, "\n\n{}", source(temporal_tokens(temporal), &lib_path)?)?;
+    }
+
+    // Add formatted values enum, dates and times are always there for the constructors
+    let temporals = Temporal::ALL.map(|t|ident(t.name()));
+    let formats = library.formats().into_iter().map(ident).collect::<Vec<Ident>>();
+    let new_fns = formats.iter().map(|f|ident(format!("new_{}", snaker(f.to_string()))));
+    let de_fns = formats.iter().map(|f|ident(format!("deserialize_{}", snaker(f.to_string()))));
+    let de_optional_fns = formats.iter().map(|f|ident(format!("deserialize_optional_{}", snaker(f.to_string()))));
+
+    write!(mod_file, "\n\n{}", source(quote! {
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        #[serde(untagged)]
+        pub enum FormattedValue {
+            #(#temporals(#temporals),)*
+            #(#formats(String),)*
+        }
+
+        impl Display for FormattedValue {
+            fn fmt(&self, f: &mut Formatter) -> FmtResult {
+                match self {
+                    #(Self::#temporals(v) => write!(f, "{}", v),)*
+                    #(Self::#formats(v) => write!(f, "{}", v),)*
+                }
+            }
+        }
+
+        impl FormattedValue {
+            #(
+                pub fn #new_fns<T>(v: T) -> Self where T: Into<String> {
+                    Self::#formats(v.into())
+                }
+            )*
+
+            // Untagged enum can't tell string variants apart, fields read their value with the
+            // function of their schema `format`
+            #(
+                #[doc(hidden)]
+                pub fn #de_fns<'de, D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+                    String::deserialize(deserializer).map(Self::#formats)
+                }
+
+                #[doc(hidden)]
+                pub fn #de_optional_fns<'de, D>(deserializer: D) -> Result<Option<Self>, D::Error> where D: serde::Deserializer<'de> {
+                    Option::<String>::deserialize(deserializer).map(|v|v.map(Self::#formats))
+                }
+            )*
+        }
+    }, &lib_path)?)?;
+
+    // Implement new Date, Time, DateTime methods
+    write!(mod_file, "\n\n{}", source(temporal_constructor_tokens(), &lib_path)?)?;
+
+    // States of required fields in typestate builders
+    if ctx.typestate_builders {
+        write!(mod_file, "\n\n{}", source(quote! {
+            /// State of required field in builder before it's set
+            #[derive(Debug, Clone, Copy, Default)]
+            pub struct Missing;
+
+            /// State of required field in builder holding its value
+            #[derive(Debug, Clone)]
+            pub struct Set<T>(pub T);
+        }, &lib_path)?)?;
+    }
+
+    // Collapsed `maxItems: 1` arrays are read and written as JSON arrays of one item
+    if ctx.collapse_single_items {
+        write!(mod_file, "\n\n{}", source(quote! {
+            #[doc(hidden)]
+            pub mod single_item {
+                pub fn serialize<T, S>(v: &T, serializer: S) -> Result<S::Ok, S::Error>
+                where T: serde::Serialize, S: serde::Serializer {
+                    serde::Serialize::serialize(std::slice::from_ref(v), serializer)
+                }
+
+                pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+                where T: serde::Deserialize<'de>, D: serde::Deserializer<'de> {
+                    let mut items = <Vec<T> as serde::Deserialize>::deserialize(deserializer)?;
+
+                    match items.len() {
+                        1 => Ok(items.remove(0)),
+                        n => Err(serde::de::Error::invalid_length(n, &"array of one item")),
+                    }
+                }
+            }
+
+            // Empty array is read as missing item
+            #[doc(hidden)]
+            pub mod optional_single_item {
+                pub fn serialize<T, S>(v: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+                where T: serde::Serialize, S: serde::Serializer {
+                    let items: &[T] = match v {
+                        Some(v) => std::slice::from_ref(v),
+                        None => &[],
+                    };
+
+                    serde::Serialize::serialize(items, serializer)
+                }
+
+                pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+                where T: serde::Deserialize<'de>, D: serde::Deserializer<'de> {
+                    let mut items = <Vec<T> as serde::Deserialize>::deserialize(deserializer)?;
+
+                    match items.len() {
+                        0 => Ok(None),
+                        1 => Ok(items.pop()),
+                        n => Err(serde::de::Error::invalid_length(n, &"array of at most one item")),
+                    }
+                }
+            }
+        }, &lib_path)?)?;
+    }
+
+    // Lenient reading removing properties that are not in the schema before deserializing
+    write!(mod_file, "\n\n{}", source(quote! {
+        /// Property that is not in the schema, removed when reading leniently
+        #[derive(Debug, Clone, PartialEq, Serialize)]
+        pub struct UnknownField {
+            /// JSON pointer of the property
+            pub pointer: String,
+            pub value: serde_json::Value,
+        }
+
+        /// Read JSON string removing properties that are not in the schema from structs that don't
+        /// allow additional properties, other reads fail on them. Removed properties are returned
+        /// with the value, they're not written back on serialization.
+        pub fn from_str_lenient<T>(s: &str) -> Result<(T, Vec<UnknownField>), serde_json::Error>
+        where T: serde::de::DeserializeOwned + Componentable<T> {
+            from_value_lenient(serde_json::from_str(s)?)
+        }
+
+        /// Read JSON value removing properties that are not in the schema, see [`from_str_lenient`]
+        pub fn from_value_lenient<T>(mut value: serde_json::Value) -> Result<(T, Vec<UnknownField>), serde_json::Error>
+        where T: serde::de::DeserializeOwned + Componentable<T> {
+            let mut removed = vec![];
+            T::remove_unknown_fields(&mut value, "", &mut removed);
+
+            serde_json::from_value(value).map(|v|(v, removed))
+        }
+
+        #[doc(hidden)]
+        pub fn remove_unlisted_fields(value: &mut serde_json::Value, known: &[&str], pointer: &str, removed: &mut Vec<UnknownField>) {
+            if let Some(map) = value.as_object_mut() {
+                let unknown = map.keys()
+                    .filter(|k|!known.contains(&k.as_str()))
+                    .cloned()
+                    .collect::<Vec<String>>();
+
+                for key in unknown {
+                    if let Some(value) = map.remove(&key) {
+                        let pointer = format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+                        removed.push(UnknownField { pointer, value });
+                    }
+                }
+            }
+        }
+
+        #[doc(hidden)]
+        pub fn remove_unknown_item_fields<T>(value: &mut serde_json::Value, pointer: &str, removed: &mut Vec<UnknownField>)
+        where T: Componentable<T> {
+            if let Some(items) = value.as_array_mut() {
+                for (i, item) in items.iter_mut().enumerate() {
+                    T::remove_unknown_fields(item, &format!("{}/{}", pointer, i), removed);
+                }
+            }
+        }
+
+        /// Removes unknown fields of choice variant from the value, true when the value then reads as the variant
+        #[doc(hidden)]
+        pub type VariantReader = fn(&mut serde_json::Value, &str, &mut Vec<UnknownField>) -> bool;
+
+        // Choice value is kept as it is when it reads as some variant, otherwise unknown fields of
+        // the first variant it reads as after removing them are removed
+        #[doc(hidden)]
+        pub fn remove_unknown_variant_fields(value: &mut serde_json::Value, pointer: &str, removed: &mut Vec<UnknownField>, variants: &[VariantReader]) {
+            let mut first = None;
+
+            for read in variants {
+                let mut variant_value = value.clone();
+                let mut variant_removed = vec![];
+
+                if read(&mut variant_value, pointer, &mut variant_removed) {
+                    if variant_removed.is_empty() {
+                        return
+                    }
+
+                    if first.is_none() {
+                        first = Some((variant_value, variant_removed));
+                    }
+                }
+            }
+
+            if let Some((variant_value, variant_removed)) = first {
+                *value = variant_value;
+                removed.extend(variant_removed);
+            }
+        }
+    }, &lib_path)?)?;
+
+    // Validation accepting codes outside the code lists, scoped to the call
+    if !library.code_lists.is_empty() {
+        write!(mod_file, "\n\n{}", source(quote! {
+            thread_local! {
+                static UNLISTED_CODES: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+            }
+
+            // Set the flag for the duration of `f` on this thread, previous value is restored also when `f` panics
+            fn scoped<R, F>(flag: &'static std::thread::LocalKey<std::cell::Cell<bool>>, value: bool, f: F) -> R where F: FnOnce() -> R {
+                struct Restore(&'static std::thread::LocalKey<std::cell::Cell<bool>>, bool);
+
+                impl Drop for Restore {
+                    fn drop(&mut self) {
+                        self.0.set(self.1);
+                    }
+                }
+
+                let _restore = Restore(flag, flag.replace(value));
+                f()
+            }
+
+            /// Validate accepting codes that are not in the code lists, e.g. codes added to a list after
+            /// the crate was generated. Applies only to this call, other validations reject them.
+            pub fn validate_allowing_unlisted_codes<T>(value: &T) -> Result<&T, UblError> where T: Componentable<T> {
+                scoped(&UNLISTED_CODES, true, ||value.validate())
+            }
+
+            /// Check everything accepting codes that are not in the code lists, see [`validate_allowing_unlisted_codes`]
+            pub fn validate_all_allowing_unlisted_codes<T>(value: &T) -> ValidationReport where T: Componentable<T> {
+                scoped(&UNLISTED_CODES, true, ||value.validate_all())
+            }
+
+            #[doc(hidden)]
+            pub fn unlisted_codes_allowed() -> bool {
+                UNLISTED_CODES.get()
+            }
+        }, &lib_path)?)?;
+    }
+
+    Ok(())
+}
+
+// Trait implemented by every generated type and wrapper returned by `new` of components
+fn componentable_tokens() -> TokenStream {
+    quote! {
+        pub trait Componentable<T> {
+            fn validate(&self) -> Result<&T, UblError>;
+            fn get(self) -> Result<T, UblError>;
+            /// Whether the struct keeps properties that are not in the schema in `additional_props`.
+            /// Other structs fail to deserialize with them unless read with `from_str_lenient`.
+            fn additional_props_allowed() -> bool;
+            /// Remove properties that are not in the schema from JSON value of this type, `pointer`
+            /// is JSON pointer of the value
+            #[doc(hidden)]
+            fn remove_unknown_fields(_value: &mut serde_json::Value, _pointer: &str, _removed: &mut Vec<UnknownField>) {}
+            /// Add every failed check of the value and the components in it to the report,
+            /// `pointer` is JSON pointer of the value
+            fn validate_into(&self, pointer: &str, report: &mut ValidationReport);
+            /// Check the value and everything in it, report lists all failures instead of the first one
+            fn validate_all(&self) -> ValidationReport {
+                let mut report = ValidationReport::default();
+                self.validate_into("", &mut report);
+                report
+            }
+        }
+
+        pub struct Component<T> (T) where T: Componentable<T> + AsMut<T> + Clone;
+
+        impl<T> Component<T> where T: Componentable<T> + AsMut<T> + Clone {
+            pub fn as_mut(&mut self) -> &mut T {
+                self.0.as_mut()
+            }
+            pub fn as_validated(&self) -> Result<&T, UblError> {
+                self.0.validate()
+            }
+            pub fn get_validated(self) -> Result<T, UblError> {
+                self.0.get()
+            }
+        }
+    }
+}
+
+// Error of failed validation with constructors taking anything convertible to the strings
+fn error_tokens() -> TokenStream {
+    quote! {
+        #[derive(Error, Debug)]
+        pub enum UblError {
+            #[error("value of `{0}` cannot be empty string")]
+            IsEmpty(String),
+            #[error("value `{0}` is optional but when provided cannot be empty string")]
+            OptionalEmpty(String),
+            #[error("unexpected format for `{input:?}`, should not be: {fmt:?}")]
+            BadFormat {
+                input: String,
+                fmt: String,
+            },
+            #[error("unable to format input `{input:?}` as {fmt:?}: {err:?}")]
+            InvalidDateTime {
+                input: String,
+                fmt: String,
+                err: String,
+            },
+            #[error("value `{input:?}` is optional but when provided should not be: {fmt:?}")]
+            OptionalBadFormat {
+                input: String,
+                fmt: String,
+            },
+            #[error("component `{item:?}` failed validation: {err:?}")]
+            ComponentValidation {
+                item: String,
+                err: String,
+            },
+            #[error("optional component `{item:?}` failed validation: {err:?}")]
+            OptionalComponentValidation {
+                item: String,
+                err: String,
+            },
+            #[error("inner item in component `{item:?}` failed validation: {err:?}")]
+            InnerComponentValidation {
+                item: String,
+                err: String,
+            },
+            #[error("namespace declaration `{key}` should be {expected:?}, found {found:?}")]
+            Namespace {
+                key: String,
+                expected: String,
+                found: Option<String>,
+            },
+            #[error("namespace declaration `{key}` is not known, found {found:?}")]
+            UnknownNamespace {
+                key: String,
+                found: String,
+            },
+            #[error("value {value:?} of `{item}` is not one of {allowed:?}")]
+            NotAllowed {
+                item: String,
+                value: String,
+                allowed: Vec<String>,
+            },
+            #[error("value {value:?} of `{item}` does not match pattern {pattern:?}")]
+            PatternMismatch {
+                item: String,
+                value: String,
+                pattern: String,
+            },
+            #[error("value of `{item}` is {len} characters long, minimum length is {min}")]
+            TooShort {
+                item: String,
+                len: usize,
+                min: usize,
+            },
+            #[error("value of `{item}` is {len} characters long, maximum length is {max}")]
+            TooLong {
+                item: String,
+                len: usize,
+                max: usize,
+            },
+            #[error("value {value} of `{item}` is less than minimum {min}")]
+            BelowMinimum {
+                item: String,
+                value: f64,
+                min: f64,
+            },
+            #[error("value {value} of `{item}` is greater than maximum {max}")]
+            AboveMaximum {
+                item: String,
+                value: f64,
+                max: f64,
+            },
+            #[error("value {value:?} of `{item}` is not in code list {list}")]
+            NotInCodeList {
+                item: String,
+                value: String,
+                list: String,
+            },
+            #[error("value of `{item}` matches none of the allowed schemas")]
+            NoMatch {
+                item: String,
+            },
+            #[error("value of `{item}` matches {matches} schemas, exactly one is allowed")]
+            AmbiguousMatch {
+                item: String,
+                matches: usize,
+            },
+            #[error("`{item}` has {len} items, minimum is {min}")]
+            TooFewItems {
+                item: String,
+                len: usize,
+                min: usize,
+            },
+            #[error("`{item}` has {len} items, maximum is {max}")]
+            TooManyItems {
+                item: String,
+                len: usize,
+                max: usize,
+            },
+            #[error("item {index} of `{item}` failed validation: {err}")]
+            ItemValidation {
+                item: String,
+                index: usize,
+                err: String,
+            },
+            #[error("value {value} of `{item}` is not a finite number")]
+            NotFinite {
+                item: String,
+                value: f64,
+            },
+        }
+
+        impl UblError {
+            pub fn date_time<I, F>(input: I, fmt: F, err: chrono::ParseError) -> Self where I: Into<String>, F: Into<String> {
+                Self::InvalidDateTime {
+                    input: input.into(),
+                    fmt: fmt.into(),
+                    err: err.to_string(),
+                }
+            }
+            pub fn empty<T>(input: T) -> Self where T: Into<String> {
+                Self::IsEmpty(input.into())
+            }
+            pub fn optional_empty<T>(input: T) -> Self where T: Into<String> {
+                Self::OptionalEmpty(input.into())
+            }
+            pub fn format<T>(input: T, fmt: &FormattedValue) -> Self where T: Into<String> {
+                Self::BadFormat {
+                    input: input.into(),
+                    fmt: fmt.to_string(),
+                }
+            }
+            pub fn optional_format<T>(input: T, fmt: &FormattedValue) -> Self where T: Into<String> {
+                Self::OptionalBadFormat {
+                    input: input.into(),
+                    fmt: fmt.to_string(),
+                }
+            }
+            pub fn component<T>(item: T, err: Self) -> Self where T: Into<String> {
+                Self::ComponentValidation {
+                    item: item.into(),
+                    err: err.to_string(),
+                }
+            }
+            pub fn optional_component<T>(item: T, err: Self) -> Self where T: Into<String> {
+                Self::OptionalComponentValidation {
+                    item: item.into(),
+                    err: err.to_string(),
+                }
+            }
+            pub fn inner_component<T, E>(item: T, err: E) -> Self where T: Into<String>, E: Into<String> {
+                Self::InnerComponentValidation {
+                    item: item.into(),
+                    err: err.into(),
+                }
+            }
+            pub fn namespace<K, E>(key: K, expected: E, found: Option<String>) -> Self where K: Into<String>, E: Into<String> {
+                Self::Namespace {
+                    key: key.into(),
+                    expected: expected.into(),
+                    found,
+                }
+            }
+            pub fn unknown_namespace<K, F>(key: K, found: F) -> Self where K: Into<String>, F: Into<String> {
+                Self::UnknownNamespace {
+                    key: key.into(),
+                    found: found.into(),
+                }
+            }
+            pub fn not_allowed<T, V>(item: T, value: V, allowed: &[&str]) -> Self where T: Into<String>, V: Into<String> {
+                Self::NotAllowed {
+                    item: item.into(),
+                    value: value.into(),
+                    allowed: allowed.iter().map(|s|s.to_string()).collect(),
+                }
+            }
+            pub fn pattern_mismatch<T, V>(item: T, value: V, pattern: &str) -> Self where T: Into<String>, V: Into<String> {
+                Self::PatternMismatch {
+                    item: item.into(),
+                    value: value.into(),
+                    pattern: pattern.to_string(),
+                }
+            }
+            pub fn too_short<T>(item: T, len: usize, min: usize) -> Self where T: Into<String> {
+                Self::TooShort {
+                    item: item.into(),
+                    len,
+                    min,
+                }
+            }
+            pub fn too_long<T>(item: T, len: usize, max: usize) -> Self where T: Into<String> {
+                Self::TooLong {
+                    item: item.into(),
+                    len,
+                    max,
+                }
+            }
+            pub fn below_minimum<T>(item: T, value: f64, min: f64) -> Self where T: Into<String> {
+                Self::BelowMinimum {
+                    item: item.into(),
+                    value,
+                    min,
+                }
+            }
+            pub fn above_maximum<T>(item: T, value: f64, max: f64) -> Self where T: Into<String> {
+                Self::AboveMaximum {
+                    item: item.into(),
+                    value,
+                    max,
+                }
+            }
+            pub fn not_in_code_list<T, V>(item: T, value: V, list: &str) -> Self where T: Into<String>, V: Into<String> {
+                Self::NotInCodeList {
+                    item: item.into(),
+                    value: value.into(),
+                    list: list.to_string(),
+                }
+            }
+            pub fn no_match<T>(item: T) -> Self where T: Into<String> {
+                Self::NoMatch {
+                    item: item.into(),
+                }
+            }
+            pub fn ambiguous_match<T>(item: T, matches: usize) -> Self where T: Into<String> {
+                Self::AmbiguousMatch {
+                    item: item.into(),
+                    matches,
+                }
+            }
+            pub fn too_few_items<T>(item: T, len: usize, min: usize) -> Self where T: Into<String> {
+                Self::TooFewItems {
+                    item: item.into(),
+                    len,
+                    min,
+                }
+            }
+            pub fn too_many_items<T>(item: T, len: usize, max: usize) -> Self where T: Into<String> {
+                Self::TooManyItems {
+                    item: item.into(),
+                    len,
+                    max,
+                }
+            }
+            pub fn item<T>(item: T, index: usize, err: Self) -> Self where T: Into<String> {
+                Self::ItemValidation {
+                    item: item.into(),
+                    index,
+                    err: err.to_string(),
+                }
+            }
+            pub fn not_finite<T>(item: T, value: f64) -> Self where T: Into<String> {
+                Self::NotFinite {
+                    item: item.into(),
+                    value,
+                }
+            }
+            /// Machine readable code of the error, e.g. `too_long`
+            pub fn code(&self) -> &'static str {
+                match self {
+                    Self::IsEmpty(_) => "is_empty",
+                    Self::OptionalEmpty(_) => "optional_empty",
+                    Self::BadFormat { .. } => "bad_format",
+                    Self::InvalidDateTime { .. } => "invalid_date_time",
+                    Self::OptionalBadFormat { .. } => "optional_bad_format",
+                    Self::ComponentValidation { .. } => "component_validation",
+                    Self::OptionalComponentValidation { .. } => "optional_component_validation",
+                    Self::InnerComponentValidation { .. } => "inner_component_validation",
+                    Self::Namespace { .. } => "namespace",
+                    Self::UnknownNamespace { .. } => "unknown_namespace",
+                    Self::NotAllowed { .. } => "not_allowed",
+                    Self::PatternMismatch { .. } => "pattern_mismatch",
+                    Self::TooShort { .. } => "too_short",
+                    Self::TooLong { .. } => "too_long",
+                    Self::BelowMinimum { .. } => "below_minimum",
+                    Self::AboveMaximum { .. } => "above_maximum",
+                    Self::NotInCodeList { .. } => "not_in_code_list",
+                    Self::NoMatch { .. } => "no_match",
+                    Self::AmbiguousMatch { .. } => "ambiguous_match",
+                    Self::TooFewItems { .. } => "too_few_items",
+                    Self::TooManyItems { .. } => "too_many_items",
+                    Self::ItemValidation { .. } => "item_validation",
+                    Self::NotFinite { .. } => "not_finite",
+                }
+            }
+        }
+    }
+}
+
+// Namespace declarations of the common libraries and envelope writing and checking them
+fn envelope_tokens() -> TokenStream {
+    let len = Literal::usize_unsuffixed(NAMESPACES.len());
+    let keys = NAMESPACES.map(|(k, _)|k);
+    let namespaces = NAMESPACES.map(|(_, n)|n);
+
+    quote! {
+        /// Namespace declarations of the common libraries written to every document, `_D` is declared per document
+        pub const NAMESPACES: [(&str, &str); #len] = [
+            #((#keys, #namespaces)),*
+        ];
+
+        /// Root of UBL document declaring its namespace in `_D` key
+        pub trait Document {
+            const NAMESPACE: &'static str;
+        }
+
+        /// Document with namespace declarations, `_D` and [`NAMESPACES`] are written on serialization
+        /// and checked on deserialization. Other declarations are not allowed by the document schemas
+        /// and fail deserialization.
+        #[derive(Debug, Clone)]
+        pub struct Envelope<T> (T) where T: Document;
+
+        impl<T> Envelope<T> where T: Document {
+            pub fn new(document: T) -> Self {
+                Self(document)
+            }
+            pub fn document(&self) -> &T {
+                &self.0
+            }
+            pub fn document_mut(&mut self) -> &mut T {
+                &mut self.0
+            }
+            pub fn into_document(self) -> T {
+                self.0
+            }
+        }
+
+        impl<T> Envelope<T> where T: Document + Componentable<T> {
+            /// Check the whole document, pointers of the report are relative to the document object
+            pub fn validate_all(&self) -> ValidationReport {
+                self.0.validate_all()
+            }
+        }
+
+        // Namespace declarations are checked on deserialization, unknown fields are removed from the document
+        impl<T> Componentable<Envelope<T>> for Envelope<T> where T: Document + Componentable<T> {
+            fn validate(&self) -> Result<&Self, UblError> {
+                self.0.validate().map(|_|self)
+            }
+
+            fn get(self) -> Result<Self, UblError> {
+                self.0.validate()?;
+                Ok(self)
+            }
+
+            fn additional_props_allowed() -> bool {
+                false
+            }
+
+            fn validate_into(&self, pointer: &str, report: &mut ValidationReport) {
+                self.0.validate_into(pointer, report)
+            }
+
+            fn remove_unknown_fields(value: &mut serde_json::Value, pointer: &str, removed: &mut Vec<UnknownField>) {
+                let declarations = match value.as_object_mut() {
+                    Some(map) => {
+                        let keys = map.keys().filter(|k|is_declaration(k)).cloned().collect::<Vec<String>>();
+                        keys.into_iter().filter_map(|k|map.remove(&k).map(|v|(k, v))).collect::<Vec<_>>()
+                    },
+                    None => return,
+                };
+
+                T::remove_unknown_fields(value, pointer, removed);
+
+                if let Some(map) = value.as_object_mut() {
+                    map.extend(declarations);
+                }
+            }
+        }
+
+        // Namespace declarations are underscore followed by single uppercase letter
+        fn is_declaration(key: &str) -> bool {
+            key.len() == 2 && key.starts_with('_') && key.chars().nth(1).is_some_and(|c| c.is_ascii_uppercase())
+        }
+
+        impl<T> From<T> for Envelope<T> where T: Document {
+            fn from(document: T) -> Self {
+                Self(document)
+            }
+        }
+
+        impl<T> Serialize for Envelope<T> where T: Document + Serialize {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+                #[derive(Serialize)]
+                struct Declared<'a, T> {
+                    #[serde(rename = "_D")]
+                    namespace: &'static str,
+                    #[serde(flatten)]
+                    namespaces: std::collections::BTreeMap<&'static str, &'static str>,
+                    #[serde(flatten)]
+                    document: &'a T,
+                }
+
+                Declared {
+                    namespace: T::NAMESPACE,
+                    namespaces: NAMESPACES.into_iter().collect(),
+                    document: &self.0,
+                }.serialize(serializer)
+            }
+        }
+
+        impl<'de, T> Deserialize<'de> for Envelope<T> where T: Document + serde::de::DeserializeOwned {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+                use serde::de::Error;
+
+                let mut map = serde_json::Map::deserialize(deserializer)?;
+                let as_string = |v: serde_json::Value| v.as_str().map(String::from).unwrap_or(v.to_string());
+
+                let found = map.remove("_D").map(as_string);
+
+                if found.as_deref() != Some(T::NAMESPACE) {
+                    return Err(D::Error::custom(UblError::namespace("_D", T::NAMESPACE, found)))
+                }
+
+                let declarations = map.keys()
+                    .filter(|k|is_declaration(k))
+                    .cloned()
+                    .collect::<Vec<String>>();
+
+                for key in declarations {
+                    let found = map.remove(&key).map(as_string);
+
+                    match NAMESPACES.iter().find(|(k, _)| k.eq(&key)) {
+                        Some((_, expected)) if found.as_deref() != Some(*expected) => {
+                            return Err(D::Error::custom(UblError::namespace(key, *expected, found)))
+                        },
+                        Some(_) => (),
+                        None => {
+                            return Err(D::Error::custom(UblError::unknown_namespace(key, found.unwrap_or_default())))
+                        },
+                    }
+                }
+
+                T::deserialize(serde_json::Value::Object(map))
+                    .map(Self)
+                    .map_err(D::Error::custom)
+            }
+        }
+    }
+}
+
+// Constructors of date and time values, from chrono types and from strings in default or custom format
+fn temporal_constructor_tokens() -> TokenStream {
+    quote! {
+        impl FormattedValue {
+            pub fn new_datetime(v: chrono::NaiveDateTime) -> Self {
+                Self::DateTime(DateTime::from(v))
+            }
+            /// Create new date time that is written with its timezone offset, e.g. `2024-01-02T10:00:00+02:00`
+            pub fn new_date_time_with_offset(v: chrono::DateTime<chrono::FixedOffset>) -> Self {
+                Self::DateTime(DateTime::from(v))
+            }
+            /// Create new date time from a string formatted as `YYYY-MM-DD HH:MM:SS`
+            pub fn new_date_time_from_str<T>(v: T) -> Result<Self, UblError> where T: AsRef<str> {
+                let s = v.as_ref();
+                let fmt = "%Y-%m-%d %H:%M:%S";
+                chrono::NaiveDateTime::parse_from_str(s, fmt)
+                    .map(|n|Self::DateTime(DateTime::from(n)))
+                    .map_err(|e|UblError::date_time(s, fmt, e))
+            }
+            /// Create new date time from a string with custom formatting (timezone, fractional seconds, etc.)
+            /// Offset parsed with `%z` or `%:z` is kept, value is written as `YYYY-MM-DDTHH:MM:SS` followed by
+            /// fractional seconds and offset when there are any
+            pub fn new_date_time_from_str_in_fmt<T, F>(v: T, format: F) -> Result<Self, UblError> where T: AsRef<str>, F: AsRef<str> {
+                let s = v.as_ref();
+                let fmt_in = format.as_ref();
+                parsed_in_fmt(s, fmt_in)
+                    .and_then(|p|Ok(DateTime {
+                        value: p.to_naive_date()?.and_time(p.to_naive_time()?),
+                        offset: p.to_fixed_offset().ok().map(TimeOffset::Fixed),
+                        fraction_digits: fraction_digits(p.to_naive_time()?),
+                    }))
+                    .map(Self::DateTime)
+                    .map_err(|e|UblError::date_time(s, fmt_in, e))
+            }
+            pub fn new_date(date: chrono::NaiveDate) -> Self {
+                Self::Date(Date::from(date))
+            }
+            pub fn new_date_from_str<T>(v: T) -> Result<Self, UblError> where T: AsRef<str> {
+                let s = v.as_ref();
+                let fmt = "%Y-%m-%d";
+                chrono::NaiveDate::parse_from_str(s, fmt)
+                    .map(|n|Self::Date(Date::from(n)))
+                    .map_err(|e|UblError::date_time(s, fmt, e))
+            }
+            /// Create new date from a string with custom formatting, offset parsed with `%z` or `%:z` is kept
+            pub fn new_date_from_str_in_fmt<T, F>(v: T, format: F) -> Result<Self, UblError> where T: AsRef<str>, F: AsRef<str> {
+                let s = v.as_ref();
+                let fmt_in = format.as_ref();
+                parsed_in_fmt(s, fmt_in)
+                    .and_then(|p|Ok(Date {
+                        value: p.to_naive_date()?,
+                        offset: p.to_fixed_offset().ok().map(TimeOffset::Fixed),
+                    }))
+                    .map(Self::Date)
+                    .map_err(|e|UblError::date_time(s, fmt_in, e))
+            }
+            pub fn new_time(time: chrono::NaiveTime) -> Self {
+                Self::Time(Time::from(time))
+            }
+            /// Create new time that is written with its timezone offset, e.g. `10:00:00+02:00`
+            pub fn new_time_with_offset(time: chrono::NaiveTime, offset: chrono::FixedOffset) -> Self {
+                Self::Time(Time::from(time).with_offset(TimeOffset::Fixed(offset)))
+            }
+            pub fn new_time_from_str<T>(v: T) -> Result<Self, UblError> where T: AsRef<str> {
+                let s = v.as_ref();
+                let fmt = "%H:%M:%S";
+                chrono::NaiveTime::parse_from_str(s, fmt)
+                    .map(|n|Self::Time(Time::from(n)))
+                    .map_err(|e|UblError::date_time(s, fmt, e))
+            }
+            /// Create new time from a string with custom formatting, offset parsed with `%z` or `%:z` is kept
+            pub fn new_time_from_str_in_fmt<T, F>(v: T, format: F) -> Result<Self, UblError> where T: AsRef<str>, F: AsRef<str> {
+                let s = v.as_ref();
+                let fmt_in = format.as_ref();
+                parsed_in_fmt(s, fmt_in)
+                    .and_then(|p|Ok(Time {
+                        value: p.to_naive_time()?,
+                        offset: p.to_fixed_offset().ok().map(TimeOffset::Fixed),
+                        fraction_digits: fraction_digits(p.to_naive_time()?),
+                    }))
+                    .map(Self::Time)
+                    .map_err(|e|UblError::date_time(s, fmt_in, e))
+            }
+        }
+    }
+}
+
+// Offset of dates, times and date times with helpers shared by them. `Z` and `+00:00` are separate so
+// that values are written as they were read.
+fn time_offset_tokens() -> TokenStream {
+    quote! {
+        /// Timezone offset of `Date`, `Time` or `DateTime`, `Utc` is written as `Z`
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum TimeOffset {
+            Utc,
+            Fixed(chrono::FixedOffset),
+        }
+
+        impl TimeOffset {
+            pub fn fixed(&self) -> chrono::FixedOffset {
+                match self {
+                    Self::Utc => chrono::FixedOffset::east_opt(0).expect("zero offset is in range"),
+                    Self::Fixed(o) => *o,
+                }
+            }
+
+            // Value without offset suffix and the offset, suffix that isn't an offset is left to the
+            // value so that parsing it fails
+            fn split(s: &str) -> (&str, Option<Self>) {
+                if let Some(rest) = s.strip_suffix('Z') {
+                    return (rest, Some(Self::Utc))
+                }
+
+                let at = s.len().saturating_sub(6);
+                let mut parsed = chrono::format::Parsed::new();
+
+                let offset = s.get(at..)
+                    .filter(|o|o.starts_with(['+', '-']))
+                    .filter(|o|chrono::format::parse(&mut parsed, o, chrono::format::StrftimeItems::new("%:z")).is_ok())
+                    .and_then(|_|parsed.to_fixed_offset().ok());
+
+                match offset {
+                    Some(o) => (&s[..at], Some(Self::Fixed(o))),
+                    None => (s, None),
+                }
+            }
+        }
+
+        impl Display for TimeOffset {
+            fn fmt(&self, f: &mut Formatter) -> FmtResult {
+                match self {
+                    Self::Utc => write!(f, "Z"),
+                    Self::Fixed(o) => write!(f, "{}", o),
+                }
+            }
+        }
+
+        // Digits needed to write fractional seconds without trailing zeros
+        fn fraction_digits<T>(time: T) -> u8 where T: chrono::Timelike {
+            let mut nanos = time.nanosecond() % 1_000_000_000;
+            let mut digits = 9;
+
+            match nanos {
+                0 => 0,
+                _ => {
+                    while nanos % 10 == 0 {
+                        nanos /= 10;
+                        digits -= 1;
+                    }
+                    digits
+                },
+            }
+        }
+
+        // Fractional seconds with given number of digits, e.g. `.50`
+        fn fraction_writer<T>(f: &mut Formatter, time: &T, digits: u8) -> FmtResult where T: chrono::Timelike {
+            match digits {
+                0 => Ok(()),
+                d => {
+                    let nanos = format!("{:09}", time.nanosecond() % 1_000_000_000);
+                    write!(f, ".{}", &nanos[..usize::from(d.min(9))])
+                },
+            }
+        }
+
+        fn parsed_in_fmt(s: &str, fmt: &str) -> Result<chrono::format::Parsed, chrono::ParseError> {
+            let mut parsed = chrono::format::Parsed::new();
+            chrono::format::parse(&mut parsed, s, chrono::format::StrftimeItems::new(fmt))?;
+            Ok(parsed)
+        }
+    }
+}
+
+// Dates, times and date times keep the offset from the input, times and date times also the
+// number of fractional second digits. Parsing fails for invalid values so they're rejected already when
+// deserializing.
+fn temporal_tokens(temporal: Temporal) -> TokenStream {
+    let name = ident(temporal.name());
+
+    let (inner, format, written, doc) = match temporal {
+        Temporal::Date => return date_tokens(),
+        Temporal::Time => (
+            quote!(chrono::NaiveTime),
+            "%H:%M:%S%.f",
+            "%H:%M:%S",
+            " Time of `format: time` field, written as `HH:MM:SS` with optional fractional seconds and offset",
+        ),
+        Temporal::DateTime => (
+            quote!(chrono::NaiveDateTime),
+            "%Y-%m-%dT%H:%M:%S%.f",
+            "%Y-%m-%dT%H:%M:%S",
+            " Date and time of `format: date-time` field, written as `YYYY-MM-DDTHH:MM:SS` with optional fractional seconds and offset",
+        ),
+    };
+
+    // Date time converts from chrono date times that have an offset
+    let from_zoned = match temporal {
+        Temporal::DateTime => quote! {
+            impl #name {
+                /// Date time in its offset, `None` for local date times
+                pub fn to_fixed_offset(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+                    self.offset.and_then(|o|self.value.and_local_timezone(o.fixed()).single())
+                }
+            }
+
+            impl From<chrono::DateTime<chrono::FixedOffset>> for #name {
+                fn from(v: chrono::DateTime<chrono::FixedOffset>) -> Self {
+                    Self::from(v.naive_local()).with_offset(TimeOffset::Fixed(*v.offset()))
+                }
+            }
+
+            impl From<chrono::DateTime<chrono::Utc>> for #name {
+                fn from(v: chrono::DateTime<chrono::Utc>) -> Self {
+                    Self::from(v.naive_utc()).with_offset(TimeOffset::Utc)
+                }
+            }
+        },
+        _ => TokenStream::new(),
+    };
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct #name {
+            pub value: #inner,
+            /// `None` for local values without offset
+            pub offset: Option<TimeOffset>,
+            /// Number of fractional second digits written, up to 9
+            pub fraction_digits: u8,
+        }
+
+        impl #name {
+            /// Format of the value without offset, offset is `Z` or `+HH:MM`
+            pub const FORMAT: &'static str = #format;
+
+            pub fn parse<T>(v: T) -> Result<Self, UblError> where T: AsRef<str> {
+                let s = v.as_ref();
+                let (value, offset) = TimeOffset::split(s);
+
+                // Digits after the decimal point of seconds
+                let fraction_digits = value.rsplit_once('.')
+                    .map(|(_, f)|f.len().min(9) as u8)
+                    .unwrap_or(0);
+
+                #inner::parse_from_str(value, Self::FORMAT)
+                    .map(|value|Self { value, offset, fraction_digits })
+                    .map_err(|e|UblError::date_time(s, Self::FORMAT, e))
+            }
+
+            pub fn with_offset(self, offset: TimeOffset) -> Self {
+                Self { offset: Some(offset), ..self }
+            }
+        }
+
+        #from_zoned
+
+        impl From<#inner> for #name {
+            fn from(value: #inner) -> Self {
+                Self { value, offset: None, fraction_digits: fraction_digits(value) }
+            }
+        }
+
+        impl std::str::FromStr for #name {
+            type Err = UblError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::parse(s)
+            }
+        }
+
+        impl Display for #name {
+            fn fmt(&self, f: &mut Formatter) -> FmtResult {
+                write!(f, "{}", self.value.format(#written))?;
+                fraction_writer(f, &self.value, self.fraction_digits)?;
+
+                match &self.offset {
+                    Some(o) => write!(f, "{}", o),
+                    None => Ok(()),
+                }
+            }
+        }
+
+        impl Serialize for #name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for #name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+                let s = String::deserialize(deserializer)?;
+                Self::parse(&s).map_err(serde::de::Error::custom)
+            }
+        }
+    }
+}
+
+fn date_tokens() -> TokenStream {
+    quote! {
+        /// Date of `format: date` field, written as `YYYY-MM-DD` with optional offset
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct Date {
+            pub value: chrono::NaiveDate,
+            /// `None` for local dates without offset
+            pub offset: Option<TimeOffset>,
+        }
+
+        impl Date {
+            /// Format of the value without offset, offset is `Z` or `+HH:MM`
+            pub const FORMAT: &'static str = "%Y-%m-%d";
+
+            pub fn parse<T>(v: T) -> Result<Self, UblError> where T: AsRef<str> {
+                let s = v.as_ref();
+                let (value, offset) = TimeOffset::split(s);
+
+                chrono::NaiveDate::parse_from_str(value, Self::FORMAT)
+                    .map(|value|Self { value, offset })
+                    .map_err(|e|UblError::date_time(s, Self::FORMAT, e))
+            }
+
+            pub fn with_offset(self, offset: TimeOffset) -> Self {
+                Self { offset: Some(offset), ..self }
+            }
+        }
+
+        impl From<chrono::NaiveDate> for Date {
+            fn from(value: chrono::NaiveDate) -> Self {
+                Self { value, offset: None }
+            }
+        }
+
+        impl std::str::FromStr for Date {
+            type Err = UblError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::parse(s)
+            }
+        }
+
+        impl Display for Date {
+            fn fmt(&self, f: &mut Formatter) -> FmtResult {
+                write!(f, "{}", self.value.format(Self::FORMAT))?;
+
+                match &self.offset {
+                    Some(o) => write!(f, "{}", o),
+                    None => Ok(()),
+                }
+            }
+        }
+
+        impl Serialize for Date {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Date {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+                let s = String::deserialize(deserializer)?;
+                Self::parse(&s).map_err(serde::de::Error::custom)
+            }
+        }
+    }
+}
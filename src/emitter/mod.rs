@@ -0,0 +1,169 @@
+mod choice;
+mod code_list;
+mod component;
+mod library;
+mod reference;
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use std::fs::create_dir_all;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use crate::ir::{Library, Module, TypeRef, ValueKinds};
+use crate::parser::{snaker, Context};
+
+pub use library::lib_writer;
+
+
+// Emitter writes rust sources from the intermediate representation. Code is built as tokens with
+// quote and printed with prettyplease, so names and strings from schemas end up escaped and
+// the output is formatted the same way regardless of the schema contents.
+
+/// Write modules of the library, code lists and `lib.rs` file to source directory
+pub fn library_writer(src_path: &Path, library: &Library, ctx: &Context, exporter: bool) -> Result<()> {
+    let value_kinds = library.value_kinds();
+
+    for module in library.modules.iter() {
+        module_writer(src_path, module, &value_kinds, ctx)?;
+    }
+
+    if !library.code_lists.is_empty() {
+        code_list::code_lists_writer(src_path, &library.code_lists)?;
+    }
+
+    lib_writer(src_path, library, ctx, exporter)
+}
+
+/// Write module directory with components, extensions and references. Value kinds of the whole
+/// library are needed for references pointing to data types in other modules.
+pub fn module_writer(src_path: &Path, module: &Module, value_kinds: &ValueKinds, ctx: &Context) -> Result<()> {
+    let out_dir = src_path.join(&module.name);
+    create_dir_all(&out_dir)?;
+
+    let mut mods = vec![];
+
+    // Choices are written next to components, they're used the same way
+    if !module.components.is_empty() || !module.choices.is_empty() {
+        let comps_dir = out_dir.join("components");
+        let mut comp_mods = vec![];
+
+        create_dir_all(&comps_dir)?;
+
+        for comp in module.components.iter() {
+            let mut tokens = component::component_tokens(comp, &module.name, ctx);
+
+            if let Some(doc) = module.document.as_ref().filter(|d|d.root.eq(&comp.name)) {
+                tokens.extend(component::document_tokens(doc, ctx));
+            }
+
+            let file_name = snaker(&comp.key);
+            file_writer(&comps_dir.join(format!("{}.rs", file_name)), tokens)?;
+            comp_mods.push(file_name);
+        }
+
+        for choice in module.choices.iter() {
+            let file_name = snaker(&choice.key);
+            file_writer(&comps_dir.join(format!("{}.rs", file_name)), choice::choice_tokens(choice, &module.name, ctx))?;
+            comp_mods.push(file_name);
+        }
+
+        file_writer(&comps_dir.join("mod.rs"), mod_tokens(&comp_mods))?;
+        mods.push(String::from("components"));
+    }
+
+    if !module.extensions.is_empty() {
+        file_writer(&out_dir.join("extensions.rs"), component::extension_tokens(&module.extensions, ctx))?;
+        mods.push(String::from("extensions"));
+    }
+
+    if !module.references.is_empty() {
+        file_writer(&out_dir.join("references.rs"), reference::reference_tokens(&module.references, &module.name, value_kinds, ctx))?;
+        mods.push(String::from("references"));
+    }
+
+    file_writer(&out_dir.join("mod.rs"), mod_tokens(&mods))
+}
+
+// Private modules with their contents re-exported
+fn mod_tokens(mods: &[String]) -> TokenStream {
+    let mods = mods.iter().map(ident).collect::<Vec<Ident>>();
+
+    quote! {
+        #(mod #mods;)*
+        #(pub use #mods::*;)*
+    }
+}
+
+// Format tokens as rust source file
+fn file_writer(path: &Path, tokens: TokenStream) -> Result<()> {
+    std::fs::write(path, source(tokens, path)?)
+}
+
+// Format tokens as rust source, path is for the error message
+fn source(tokens: TokenStream, path: &Path) -> Result<String> {
+    syn::parse2::<syn::File>(tokens)
+        .map(|f|prettyplease::unparse(&f))
+        .map_err(|e|Error::new(ErrorKind::InvalidData, format!("invalid source generated for {:?}: {}", path, e)))
+}
+
+// Path keywords can't be written as raw identifiers
+const PATH_KEYWORDS: [&str; 4] = ["self", "Self", "super", "crate"];
+
+/// Identifier from schema derived name, keywords are written as raw identifiers and path keywords
+/// are suffixed with underscore, e.g. `self_`
+fn ident<T>(name: T) -> Ident where T: AsRef<str> {
+    let name = name.as_ref();
+
+    match syn::parse_str::<Ident>(name) {
+        Ok(_) => Ident::new(name, Span::call_site()),
+        Err(_) if PATH_KEYWORDS.contains(&name) => Ident::new(&format!("{}_", name), Span::call_site()),
+        Err(_) => Ident::new_raw(name, Span::call_site()),
+    }
+}
+
+/// Path of the module generated modules are declared in, e.g. `crate::ubl`
+fn root_path(ctx: &Context) -> TokenStream {
+    let root = ctx.root_mod;
+    quote!(#root)
+}
+
+/// Full path of referenced struct from within given module
+fn type_path(ty: &TypeRef, module: &str, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let module = ident(ty.module.as_deref().unwrap_or(module));
+    let name = ident(&ty.name);
+
+    quote!(#root::#module::#name)
+}
+
+/// Methods returning static title and description
+fn title_and_descr_tokens(title: Option<&String>, description: Option<&String>) -> TokenStream {
+    let title = title.map(|s|quote! {
+        pub fn title() -> &'static str {
+            #s
+        }
+    });
+
+    let description = description.map(|s|quote! {
+        pub fn description() -> &'static str {
+            #s
+        }
+    });
+
+    quote!(#title #description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_are_escaped() {
+        assert_eq!(ident("type").to_string(), "r#type");
+        assert_eq!(ident("self").to_string(), "self_");
+        assert_eq!(ident("Self").to_string(), "Self_");
+        assert_eq!(ident("crate").to_string(), "crate_");
+        assert_eq!(ident("amount").to_string(), "amount");
+    }
+}
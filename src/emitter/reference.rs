@@ -0,0 +1,306 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::ir::{Reference, ValueKind, ValueKinds};
+use crate::parser::Context;
+
+use super::{ident, root_path, title_and_descr_tokens, type_path};
+
+
+/// Type aliases for plain references and newtypes for references with title, description or code
+/// list. Plain references are newtypes too when reference newtypes are enabled.
+pub fn reference_tokens(references: &[Reference], module: &str, value_kinds: &ValueKinds, ctx: &Context) -> TokenStream {
+    let mut tokens = TokenStream::new();
+
+    for r in references {
+        let name = ident(&r.name);
+        let target = type_path(&r.target, module, ctx);
+        let value_kind = value_kinds.get(&(module.to_owned(), r.name.to_owned()));
+
+        // Code is checked from `_` of the target, so code list applies to data types holding text value
+        let code_list = r.code_list.as_ref().filter(|_|value_kind.eq(&Some(&ValueKind::Text)));
+        let is_plain = r.title.is_none() && r.description.is_none() && code_list.is_none();
+
+        if is_plain && !ctx.reference_newtypes {
+            tokens.extend(quote! {
+                pub type #name = #target;
+            });
+
+            continue;
+        }
+
+        let title_and_descr = title_and_descr_tokens(r.title.as_ref(), r.description.as_ref());
+        let code_list_value = code_list.map(|c|code_list_value_tokens(c, ctx));
+        let componentable = match code_list {
+            Some(c) => code_list_tokens(&r.name, c, &target, ctx),
+            None => newtype_tokens(&r.name, &target, ctx),
+        };
+        let conversions = conversion_tokens(&r.name, &target);
+        let value_conversions = value_kind
+            .map(|k|value_conversion_tokens(&r.name, code_list.map(|c|c.as_str()), &target, k, ctx));
+
+        let methods = match is_plain {
+            true => TokenStream::new(),
+            false => quote! {
+                impl #name {
+                    #title_and_descr
+                    #code_list_value
+                }
+            },
+        };
+
+        tokens.extend(quote! {
+            #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+            #[serde(transparent)]
+            pub struct #name(pub #target);
+
+            #methods
+
+            #conversions
+
+            #value_conversions
+
+            #componentable
+        });
+    }
+
+    tokens
+}
+
+// Newtype converts from and into its target and derefs to it
+fn conversion_tokens(name: &str, target: &TokenStream) -> TokenStream {
+    let name = ident(name);
+
+    quote! {
+        impl From<#target> for #name {
+            fn from(v: #target) -> Self {
+                Self(v)
+            }
+        }
+
+        impl From<#name> for #target {
+            fn from(v: #name) -> Self {
+                v.0
+            }
+        }
+
+        impl std::ops::Deref for #name {
+            type Target = #target;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl std::ops::DerefMut for #name {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.0
+            }
+        }
+    }
+}
+
+// Newtype of data type holding single value converts from the bare value through its target.
+// Code list values convert to text codes.
+fn value_conversion_tokens(name: &str, code_list: Option<&str>, target: &TokenStream, kind: &ValueKind, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let name = ident(name);
+
+    match kind {
+        ValueKind::Text => {
+            let code_list = code_list.map(|c|{
+                let code_list = ident(c);
+
+                quote! {
+                    impl From<#root::code_lists::#code_list> for #name {
+                        fn from(v: #root::code_lists::#code_list) -> Self {
+                            Self::from(v.code())
+                        }
+                    }
+                }
+            });
+
+            quote! {
+                impl From<&str> for #name {
+                    fn from(v: &str) -> Self {
+                        Self(#target::from(v))
+                    }
+                }
+
+                impl From<String> for #name {
+                    fn from(v: String) -> Self {
+                        Self(#target::from(v))
+                    }
+                }
+
+                #code_list
+            }
+        },
+        ValueKind::Temporal(t) => {
+            let temporal = ident(t.name());
+
+            quote! {
+                impl From<#root::#temporal> for #name {
+                    fn from(v: #root::#temporal) -> Self {
+                        Self(#target::from(v))
+                    }
+                }
+
+                impl std::convert::TryFrom<&str> for #name {
+                    type Error = #root::UblError;
+
+                    fn try_from(v: &str) -> Result<Self, #root::UblError> {
+                        <#target as std::convert::TryFrom<&str>>::try_from(v).map(Self)
+                    }
+                }
+            }
+        },
+        ValueKind::Number { unit: None } => quote! {
+            impl From<serde_json::Number> for #name {
+                fn from(v: serde_json::Number) -> Self {
+                    Self(#target::from(v))
+                }
+            }
+
+            impl From<i64> for #name {
+                fn from(v: i64) -> Self {
+                    Self(#target::from(v))
+                }
+            }
+
+            impl std::convert::TryFrom<f64> for #name {
+                type Error = #root::UblError;
+
+                fn try_from(v: f64) -> Result<Self, #root::UblError> {
+                    <#target as std::convert::TryFrom<f64>>::try_from(v).map(Self)
+                }
+            }
+        },
+        ValueKind::Number { unit: Some(_) } => quote! {
+            impl<U> From<(serde_json::Number, U)> for #name where U: Into<String> {
+                fn from(v: (serde_json::Number, U)) -> Self {
+                    Self(#target::from(v))
+                }
+            }
+
+            impl<U> From<(i64, U)> for #name where U: Into<String> {
+                fn from(v: (i64, U)) -> Self {
+                    Self(#target::from(v))
+                }
+            }
+
+            impl<U> std::convert::TryFrom<(f64, U)> for #name where U: Into<String> {
+                type Error = #root::UblError;
+
+                fn try_from(v: (f64, U)) -> Result<Self, #root::UblError> {
+                    <#target as std::convert::TryFrom<(f64, U)>>::try_from(v).map(Self)
+                }
+            }
+        },
+    }
+}
+
+// Newtype is validated as its target
+fn newtype_tokens(name: &str, target: &TokenStream, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let name = ident(name);
+
+    quote! {
+        impl AsMut<#name> for #name {
+            fn as_mut(&mut self) -> &mut Self {
+                self
+            }
+        }
+
+        impl #root::Componentable<#name> for #name {
+            fn validate(&self) -> Result<&Self, #root::UblError> {
+                <#target as #root::Componentable<#target>>::validate(&self.0).map(|_|self)
+            }
+
+            fn get(self) -> Result<Self, #root::UblError> {
+                self.validate().map(|s|s.clone())
+            }
+
+            fn additional_props_allowed() -> bool {
+                <#target as #root::Componentable<#target>>::additional_props_allowed()
+            }
+
+            fn validate_into(&self, pointer: &str, report: &mut #root::ValidationReport) {
+                <#target as #root::Componentable<#target>>::validate_into(&self.0, pointer, report)
+            }
+
+            fn remove_unknown_fields(value: &mut serde_json::Value, pointer: &str, removed: &mut Vec<#root::UnknownField>) {
+                <#target as #root::Componentable<#target>>::remove_unknown_fields(value, pointer, removed)
+            }
+        }
+    }
+}
+
+// Newtype is validated as its target and the code in `_` against code list, unless unlisted
+// codes are allowed
+fn code_list_tokens(name: &str, code_list: &str, target: &TokenStream, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let path = format!("{}._uc", name);
+    let name = ident(name);
+    let code_list = ident(code_list);
+
+    // Code is read through deref, target may be another newtype
+    quote! {
+        impl #name {
+            fn validate_code(&self) -> Result<(), #root::UblError> {
+                let code = self._uc.as_str();
+
+                match #root::unlisted_codes_allowed() || #root::code_lists::#code_list::from_code(code).is_some() {
+                    true => Ok(()),
+                    false => Err(#root::UblError::not_in_code_list(#path, code, #root::code_lists::#code_list::SHORT_NAME)),
+                }
+            }
+        }
+
+        impl AsMut<#name> for #name {
+            fn as_mut(&mut self) -> &mut Self {
+                self
+            }
+        }
+
+        impl #root::Componentable<#name> for #name {
+            fn validate(&self) -> Result<&Self, #root::UblError> {
+                <#target as #root::Componentable<#target>>::validate(&self.0)?;
+                self.validate_code().map(|_|self)
+            }
+
+            fn get(self) -> Result<Self, #root::UblError> {
+                self.validate().map(|s|s.clone())
+            }
+
+            fn additional_props_allowed() -> bool {
+                <#target as #root::Componentable<#target>>::additional_props_allowed()
+            }
+
+            // Code is reported at `_`
+            fn validate_into(&self, pointer: &str, report: &mut #root::ValidationReport) {
+                <#target as #root::Componentable<#target>>::validate_into(&self.0, pointer, report);
+
+                if let Err(e) = self.validate_code() {
+                    report.push(format!("{}/_", pointer), e);
+                }
+            }
+
+            fn remove_unknown_fields(value: &mut serde_json::Value, pointer: &str, removed: &mut Vec<#root::UnknownField>) {
+                <#target as #root::Componentable<#target>>::remove_unknown_fields(value, pointer, removed)
+            }
+        }
+    }
+}
+
+fn code_list_value_tokens(code_list: &str, ctx: &Context) -> TokenStream {
+    let root = root_path(ctx);
+    let code_list = ident(code_list);
+
+    quote! {
+        /// Code in `_` as code list value
+        pub fn code_list_value(&self) -> #root::code_lists::#code_list {
+            #root::code_lists::#code_list::from(self._uc.as_str())
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+
+#[derive(Error, Debug)]
+pub enum BuilderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("unable to read schema {file:?}: {err}")]
+    Read {
+        file: PathBuf,
+        err: std::io::Error,
+    },
+    #[error("unable to parse schema {file:?}: {err}")]
+    Parse {
+        file: PathBuf,
+        err: serde_json::Error,
+    },
+    #[error("schema {file:?} references `{reference}` which is not found from schema sources")]
+    MissingSchema {
+        file: PathBuf,
+        reference: String,
+    },
+    #[error("schema file name `{0}` is found more than once from schema sources")]
+    DuplicateSchema(String),
+    #[error("schemas reference each other in a cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    #[error("unable to read code list {file:?}: {reason}")]
+    CodeList {
+        file: PathBuf,
+        reason: String,
+    },
+    #[error("code list `{0}` is found more than once from code list sources")]
+    DuplicateCodeList(String),
+    #[error("root module `{0}` is not a valid rust path")]
+    RootModule(String),
+    #[error("{} errors found from schemas:\n{}", .0.len(), .0.iter().map(|e|e.to_string()).collect::<Vec<String>>().join("\n"))]
+    Schema(Vec<SchemaError>),
+}
+
+/// Unsupported or malformed value in schema file
+#[derive(Error, Debug, Clone)]
+#[error("{file:?} at {pointer}: {reason}")]
+pub struct SchemaError {
+    pub file: PathBuf,
+    /// JSON pointer to the value, e.g. `#/definitions/Address/properties/ID/items`
+    pub pointer: String,
+    pub reason: String,
+}
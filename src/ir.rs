@@ -0,0 +1,332 @@
+// Intermediate representation of the generated library.
+// Parsers read schema files into these types and emitter writes rust sources from them,
+// neither of them has to know about the other.
+// Collections are vectors in schema order (definitions and properties sorted by key, modules in
+// dependency order) and emitter writes them in that order, so the output is reproducible.
+
+use std::collections::HashMap;
+
+/// Everything generated from the schema files, modules in processing order
+#[derive(Debug, Clone, Default)]
+pub struct Library {
+    pub modules: Vec<Module>,
+    /// Enums from Genericode files, in file name order
+    pub code_lists: Vec<CodeList>,
+}
+
+/// Module generated from single schema file
+#[derive(Debug, Clone)]
+pub struct Module {
+    /// Module name, e.g. `ubl_commonbasiccomponents_2_1`
+    pub name: String,
+    pub components: Vec<Component>,
+    pub extensions: Vec<Extension>,
+    pub references: Vec<Reference>,
+    /// Enums from `oneOf` and `anyOf` definitions
+    pub choices: Vec<Choice>,
+    /// Document declared in maindoc schema
+    pub document: Option<Document>,
+}
+
+/// Struct generated from object definition
+#[derive(Debug, Clone)]
+pub struct Component {
+    /// Definition key in schema, e.g. `AmountType`
+    pub key: String,
+    pub name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub additional_props: bool,
+    pub fields: Vec<Field>,
+}
+
+/// Struct field generated from object property
+#[derive(Debug, Clone)]
+pub struct Field {
+    /// Property key in schema, field is serialized with it
+    pub key: String,
+    pub name: String,
+    pub required: bool,
+    pub kind: FieldKind,
+    pub constraints: Constraints,
+}
+
+/// Restrictions on field value, checked in `Componentable::validate`
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    /// Allowed values from `enum`
+    pub allowed: Option<Vec<String>>,
+    pub pattern: Option<String>,
+    pub min_length: Option<u64>,
+    pub max_length: Option<u64>,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub enum FieldKind {
+    Text,
+    /// String with format, value is the `FormattedValue` variant, e.g. `Uri`
+    Formatted(String),
+    /// String with `date`, `time` or `date-time` format, parsed into date and time type
+    Temporal(Temporal),
+    Number,
+    Integer,
+    Boolean,
+    Array(Array),
+    /// Inline object property, generated as nested struct named after its parent
+    Object(Box<Component>),
+    /// Inline `oneOf` or `anyOf` property, generated as nested enum named after its parent
+    Choice(Box<Choice>),
+}
+
+/// Untagged enum generated from `oneOf` or `anyOf`, variant per branch
+#[derive(Debug, Clone)]
+pub struct Choice {
+    /// Definition key in schema, or parent key followed by property name for inline choices
+    pub key: String,
+    pub name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// `oneOf` when true and exactly one branch has to match, `anyOf` needs at least one
+    pub exclusive: bool,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub name: String,
+    pub kind: VariantKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum VariantKind {
+    Text,
+    Number,
+    Integer,
+    Boolean,
+    /// Referenced struct, `validated` when it's a component with `validate` method
+    Ref { target: TypeRef, validated: bool },
+    /// Inline object branch, generated as nested struct named after the enum
+    Object(Box<Component>),
+}
+
+/// Date and time types of the library runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Temporal {
+    Date,
+    Time,
+    DateTime,
+}
+
+impl Temporal {
+    pub const ALL: [Temporal; 3] = [Temporal::Date, Temporal::Time, Temporal::DateTime];
+
+    /// Temporal of the schema `format`, `None` for other formats
+    pub fn from_format(format: &str) -> Option<Self> {
+        match format {
+            "date" => Some(Self::Date),
+            "time" => Some(Self::Time),
+            "date-time" => Some(Self::DateTime),
+            _ => None,
+        }
+    }
+    /// Name of the type in runtime and `FormattedValue` variant
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Date => "Date",
+            Self::Time => "Time",
+            Self::DateTime => "DateTime",
+        }
+    }
+}
+
+/// Single value held in `_` of a data type, e.g. `TextType` or `AmountType`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueKind {
+    Text,
+    Temporal(Temporal),
+    /// Number and name of the unit field, `currencyID` of amounts or `unitCode` of quantities
+    Number { unit: Option<String> },
+}
+
+/// Value kinds of components and references resolving to them, keyed by module and name
+pub type ValueKinds = HashMap<(String, String), ValueKind>;
+
+/// Wrapper struct generated for array property
+#[derive(Debug, Clone)]
+pub struct Array {
+    pub name: String,
+    pub item: TypeRef,
+    pub min_items: Option<u64>,
+    pub max_items: Option<u64>,
+    /// Generated as the item itself instead of wrapper struct, set when single items are collapsed
+    pub single: Option<SingleItem>,
+}
+
+/// How array with at most one item is generated when it's collapsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SingleItem {
+    Item,
+    /// Item is a struct or holds the struct the array is in, kept on heap like in the wrapper
+    Boxed,
+}
+
+/// Empty struct generated from object definition without properties
+#[derive(Debug, Clone)]
+pub struct Extension {
+    pub name: String,
+}
+
+/// Type alias, or newtype when there's title or description, generated from `$ref` definition
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub target: TypeRef,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Enum name of the code list the value is validated against, e.g. `CurrencyCode`
+    pub code_list: Option<String>,
+}
+
+/// Enum generated from Genericode code list
+#[derive(Debug, Clone)]
+pub struct CodeList {
+    /// Enum name, e.g. `CurrencyCode`
+    pub name: String,
+    /// `ShortName` of the list identification
+    pub short_name: String,
+    pub long_name: Option<String>,
+    pub version: Option<String>,
+    pub canonical_uri: Option<String>,
+    pub codes: Vec<Code>,
+}
+
+/// Row of code list
+#[derive(Debug, Clone)]
+pub struct Code {
+    /// Code as written in documents, e.g. `EUR`
+    pub value: String,
+    /// Enum variant name
+    pub variant: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Document root of maindoc schema
+#[derive(Debug, Clone)]
+pub struct Document {
+    /// Document name, e.g. `Invoice`
+    pub name: String,
+    /// Name of the root component, e.g. `InvoiceDocument`
+    pub root: String,
+}
+
+/// Struct referenced from a module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeRef {
+    /// Module of the struct, `None` when in the same module
+    pub module: Option<String>,
+    pub name: String,
+}
+
+impl Library {
+    /// `FormattedValue` variants in the order they're first used
+    pub fn formats(&self) -> Vec<String> {
+        let mut formats: Vec<String> = vec![];
+
+        for module in self.modules.iter() {
+            for comp in module.components.iter() {
+                comp.formats(&mut formats);
+            }
+
+            for choice in module.choices.iter() {
+                choice.formats(&mut formats);
+            }
+        }
+
+        formats
+    }
+
+    /// Value kinds of data types, references are followed to the component they point to
+    pub fn value_kinds(&self) -> ValueKinds {
+        let mut kinds = ValueKinds::new();
+
+        for module in self.modules.iter() {
+            for comp in module.components.iter() {
+                if let Some(k) = comp.value_kind() {
+                    kinds.insert((module.name.to_owned(), comp.name.to_owned()), k);
+                }
+            }
+        }
+
+        // References may point to other references, repeat until nothing new is resolved
+        let mut resolved = true;
+
+        while resolved {
+            resolved = false;
+
+            for module in self.modules.iter() {
+                for r in module.references.iter() {
+                    let key = (module.name.to_owned(), r.name.to_owned());
+                    let target = (r.target.module.as_ref().unwrap_or(&module.name).to_owned(), r.target.name.to_owned());
+
+                    if kinds.contains_key(&key) {
+                        continue;
+                    }
+
+                    if let Some(k) = kinds.get(&target).cloned() {
+                        kinds.insert(key, k);
+                        resolved = true;
+                    }
+                }
+            }
+        }
+
+        kinds
+    }
+}
+
+impl Component {
+    /// Kind of the required `_` value when it's the only required field, numbers may also
+    /// require their unit
+    pub fn value_kind(&self) -> Option<ValueKind> {
+        let value = self.fields.iter().find(|f|f.key == "_" && f.required)?;
+        let unit = self.fields.iter()
+            .find(|f|["currencyID", "unitCode"].contains(&f.key.as_str()) && matches!(f.kind, FieldKind::Text));
+        let unit_key = unit.map(|u|u.key.as_str());
+        let others = self.fields.iter().filter(|f|f.required && f.key != "_").collect::<Vec<&Field>>();
+
+        match &value.kind {
+            FieldKind::Text if others.is_empty() => Some(ValueKind::Text),
+            FieldKind::Temporal(t) if others.is_empty() => Some(ValueKind::Temporal(*t)),
+            FieldKind::Number if others.iter().all(|f|Some(f.key.as_str()) == unit_key) => {
+                Some(ValueKind::Number { unit: unit.map(|u|u.name.to_owned()) })
+            },
+            _ => None,
+        }
+    }
+
+    // Add formats used by the fields of component and its nested components
+    fn formats(&self, formats: &mut Vec<String>) {
+        for field in self.fields.iter() {
+            match &field.kind {
+                FieldKind::Formatted(f) if !formats.contains(f) => formats.push(f.to_owned()),
+                FieldKind::Object(c) => c.formats(formats),
+                FieldKind::Choice(c) => c.formats(formats),
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Choice {
+    // Add formats used by the nested components of variants
+    fn formats(&self, formats: &mut Vec<String>) {
+        for variant in self.variants.iter() {
+            if let VariantKind::Object(c) = &variant.kind {
+                c.formats(formats);
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Generates rust types from [UBL 2.1 JSON schemas](https://docs.oasis-open.org/ubl/UBL-2.1-JSON/v1.0/UBL-2.1-JSON-v1.0.html).
+//!
+//! Used by `ubl-rs-builder` binary to create [ubl-rs](https://github.com/hienohomma/ubl-rs) crate,
+//! but can also be called from a build script to generate the types into `OUT_DIR`:
+//!
+//! ```no_run
+//! // build.rs, in fn main()
+//! ubl_rs_builder::Builder::new()
+//!     .schema_dir("schemas")
+//!     .root_module("crate::ubl")
+//!     .generate()
+//!     .expect("Failed to generate UBL types");
+//! ```
+//!
+//! Generated sources are then included in a module matching the root module path:
+//!
+//! ```ignore
+//! pub mod ubl {
+//!     include!(concat!(env!("OUT_DIR"), "/lib.rs"));
+//! }
+//! ```
+//!
+//! Generated code depends on `serde`, `serde_json`, `chrono` and `thiserror` crates, and on
+//! `regex` when schemas have `pattern` keywords.
+
+mod builder;
+mod diff;
+mod emitter;
+mod error;
+mod ir;
+mod output;
+mod parser;
+mod report;
+mod schema;
+
+pub use builder::Builder;
+pub use diff::{Difference, DifferenceKind};
+pub use error::{BuilderError, SchemaError};
+pub use parser::ModuleNaming;
+pub use report::{Report, UnhandledKeyword};
@@ -1,361 +1,172 @@
-// mod schema;
-mod parser;
+mod cli;
 
-use std::io::Write;
-use std::path::PathBuf;
+use clap::Parser;
+use std::path::{Path, PathBuf};
 
-use parser::{component_structifier, snaker};
+use cli::{CheckArgs, Cli, Command, GenerateArgs, InspectArgs};
+use ubl_rs_builder::{Builder, DifferenceKind};
 
 
 fn main() {
-    let in_path = PathBuf::from("./schemas/BDNDR-CCTS_CCT_SchemaModule-1.1.json");
-    let mut formats = vec![];
-    let mut schemas = vec![];
-    let mut post_processables = vec![];
-    
-    // Create schema lib directory and Cargo.toml file
-    let out_path = PathBuf::from("../ubl-rs");
-    std::fs::create_dir_all(&out_path)
-        .expect("Failed to create ubl-rs library directory");
-
-    let mut cargo_file_path = out_path.to_owned();
-    cargo_file_path.push("Cargo.toml");
-    
-    let mut cargo_file = std::fs::File::create(&cargo_file_path)
-        .expect("Failed to create file");
-
-    // Write lib details and dependencies to Cargo.toml file
-    writeln!(cargo_file, "[package]
-name = \"ubl-rs\"
-version = \"0.1.0\"
-edition = \"2021\"
-
-# See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
-
-[dependencies]
-thiserror = {{ version = \"1.0.61\" }}
-chrono = {{ version = \"0.4.38\" }}
-serde = {{ version = \"1.0.197\", features = [\"derive\"] }}
-serde_json = \"1.0.115\"").unwrap();
-
-    // Add readme file to ubl-rs directory
-    let mut readme_path = out_path.to_owned();
-    readme_path.push("README.md");
-
-    // Add license file to ubl-rs directory
-    let mut license_path = out_path.to_owned();
-    license_path.push("LICENSE");
-
-    // Add .gitignore file to ubl-rs directory
-    let mut license_path = out_path.to_owned();
-    license_path.push(".gitignore");
-    
-    // Read license and readme file from ubl-rs-out directory
-    let readme_out = PathBuf::from("./ubl-rs-out/README.md");
-    let license_out = PathBuf::from("./ubl-rs-out/LICENSE");
-    let gitignore_out = PathBuf::from("./ubl-rs-out/.gitignore");
-
-    // Copy readme file to ubl-rs directory
-    std::fs::copy(&readme_out, &readme_path)
-        .expect("Failed to copy ubl-rs README.md file from ./ubl-rs-out directory");
-
-    std::fs::copy(&license_out, &license_path)
-        .expect("Failed to copy ubl-rs LICENSE file from ./ubl-rs-out directory");
-
-    std::fs::copy(&gitignore_out, &license_path)
-        .expect("Failed to copy ubl-rs .gitignore file from ./ubl-rs-out directory");
-
-    // Build and save library
-    let out_path = "../ubl-rs/src";
-    let t = component_structifier(in_path, out_path);
-    schemas.push(t.0);
-    formats.extend(t.1);
-    post_processables.extend(t.2);
-
-    let in_path = PathBuf::from("./schemas/BDNDR-UnqualifiedDataTypes-1.1.json");
-    let t =  component_structifier(in_path, out_path);
-    schemas.push(t.0);
-    formats.extend(t.1);
-    post_processables.extend(t.2);
-
-    let in_path = PathBuf::from("./schemas/UBL-CommonBasicComponents-2.1.json");
-    let t =  component_structifier(in_path, out_path);
-    schemas.push(t.0);
-    formats.extend(t.1);
-    post_processables.extend(t.2);
-
-    let in_path = PathBuf::from("./schemas/UBL-CommonAggregateComponents-2.1.json");
-    let t =  component_structifier(in_path, out_path);
-    schemas.push(t.0);
-    formats.extend(t.1);
-    post_processables.extend(t.2);
-
-    let in_path = PathBuf::from("./schemas/UBL-CommonExtensionComponents-2.1.json");
-    let t =  component_structifier(in_path, out_path);
-    schemas.push(t.0);
-    formats.extend(t.1);
-    post_processables.extend(t.2);
-
-    let in_path = PathBuf::from("./schemas/UBL-QualifiedDataTypes-2.1.json");
-    let t =  component_structifier(in_path, out_path);
-    schemas.push(t.0);
-    formats.extend(t.1);
-    post_processables.extend(t.2);
-
-    let in_path = PathBuf::from("./schemas/UBL-ExtensionContentDataType-2.1.json");
-    let t =  component_structifier(in_path, out_path);
-    schemas.push(t.0);
-    formats.extend(t.1);
-    post_processables.extend(t.2);
-
-    formats.dedup();
-
-    // Create lib.rs file in ubl-rs directory
-    let mut mod_file_path = PathBuf::from(out_path);
-    mod_file_path.push("lib.rs");
-
-    let mut mod_file = std::fs::File::create(&mod_file_path)
-        .expect("Failed to create file");
-
-    writeln!(mod_file, "pub mod exporter;").unwrap();
-
-    for schema in schemas {
-        writeln!(mod_file, "pub mod {};", schema).unwrap();
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Generate(args) => generate(&args),
+        Command::Check(args) => check(&args),
+        Command::Inspect(args) => inspect(&args),
+    }
+}
+
+fn generate(args: &GenerateArgs) {
+    let builder = args.package.builder(&args.schema, &args.out);
+
+    match builder.generate() {
+        // Schema keywords no code was generated for
+        Ok(report) => eprint!("{}", report),
+        Err(e) => {
+            eprintln!("Failed to generate library to {:?}: {}", args.out, e);
+            std::process::exit(1);
+        },
+    }
+}
+
+fn check(args: &CheckArgs) {
+    let mut failed = false;
+    let builder = args.schema.builder();
+
+    for in_path in schema_files(&builder) {
+        let schema_file = in_path.display();
+
+        match read_definitions(&in_path) {
+            Ok((definitions, _)) => println!("ok     {} ({} definitions)", schema_file, definitions.len()),
+            Err(e) => {
+                println!("failed {}: {}", schema_file, e);
+                failed = true;
+            },
+        }
     }
 
-    writeln!(mod_file, "\n\nuse thiserror::Error;").unwrap();
-    writeln!(mod_file, "\n\nuse serde::{{Deserialize, Serialize}};").unwrap();
-    writeln!(mod_file, "use std::fmt::{{Display, Formatter, Result as FmtResult}};").unwrap();
-
-    // Add trait and wrapper struct for created components + Error handling
-    writeln!(mod_file, "\n\npub trait Componentable<T> {{
-    fn validate(&self) -> Result<&T, UblError>;
-    fn get(self) -> Result<T, UblError>;
-    /// To add additional props to struct, read it as JSON first and manipulate json object props.
-    fn additional_props_allowed() -> bool;
-}}
-
-pub struct Component<T> (T) where T: Componentable<T> + AsMut<T> + Clone;
-
-impl<T> Component<T> where T: Componentable<T> + AsMut<T> + Clone {{
-    pub fn as_mut(&mut self) -> &mut T {{
-        self.0.as_mut()
-    }}
-    pub fn as_validated(&self) -> Result<&T, UblError> {{
-        self.0.validate()
-    }}
-    pub fn get_validated(self) -> Result<T, UblError> {{
-        self.0.get()
-    }}
-}}
-
-#[derive(Error, Debug)]
-pub enum UblError {{
-    #[error(\"value of `{{0}}` cannot be empty string\")]
-    IsEmpty(String),
-    #[error(\"value `{{0}}` is optional but when provided cannot be empty string\")]
-    OptionalEmpty(String),
-    #[error(\"unexpected format for `{{input:?}}`, should not be: {{fmt:?}}\")]
-    BadFormat {{
-        input: String,
-        fmt: String,
-    }},
-    #[error(\"unable to format input `{{input:?}}` as {{fmt:?}}: {{err:?}}\")]
-    InvalidDateTime {{
-        input: String,
-        fmt: String,
-        err: String,
-    }},
-    #[error(\"value `{{input:?}}` is optional but when provided should not be: {{fmt:?}}\")]
-    OptionalBadFormat {{
-        input: String,
-        fmt: String,
-    }},
-    #[error(\"component `{{item:?}}` failed validation: {{err:?}}\")]
-    ComponentValidation {{
-        item: String,
-        err: String,
-    }},
-    #[error(\"optional component `{{item:?}}` failed validation: {{err:?}}\")]
-    OptionalComponentValidation {{
-        item: String,
-        err: String,
-    }},
-    #[error(\"inner item in component `{{item:?}}` failed validation: {{err:?}}\")]
-    InnerComponentValidation {{
-        item: String,
-        err: String,
-    }},
-}}
-
-impl UblError {{
-    pub fn date_time<I, F>(input: I, fmt: F, err: chrono::ParseError) -> Self where I: Into<String>, F: Into<String> {{
-        Self::InvalidDateTime {{
-            input: input.into(),
-            fmt: fmt.into(),
-            err: err.to_string(),
-        }}
-    }}
-    pub fn empty<T>(input: T) -> Self where T: Into<String> {{
-        Self::IsEmpty(input.into())
-    }}
-    pub fn optional_empty<T>(input: T) -> Self where T: Into<String> {{
-        Self::OptionalEmpty(input.into())
-    }}
-    pub fn format<T>(input: T, fmt: &FormattedValue) -> Self where T: Into<String> {{
-        Self::BadFormat {{
-            input: input.into(),
-            fmt: fmt.to_string(),
-        }}
-    }}
-    pub fn optional_format<T>(input: T, fmt: &FormattedValue) -> Self where T: Into<String> {{
-        Self::OptionalBadFormat {{
-            input: input.into(),
-            fmt: fmt.to_string(),
-        }}
-    }}
-    pub fn component<T>(item: T, err: Self) -> Self where T: Into<String> {{
-        Self::ComponentValidation {{
-            item: item.into(),
-            err: err.to_string(),
-        }}
-    }}
-    pub fn optional_component<T>(item: T, err: Self) -> Self where T: Into<String> {{
-        Self::OptionalComponentValidation {{
-            item: item.into(),
-            err: err.to_string(),
-        }}
-    }}
-    pub fn inner_component<T, E>(item: T, err: E) -> Self where T: Into<String>, E: Into<String> {{
-        Self::InnerComponentValidation {{
-            item: item.into(),
-            err: err.into(),
-        }}
-    }}
-}}").unwrap();
-
-    // Add formatted values enum
-    writeln!(mod_file, "\n\n#[derive(Debug, Clone, Serialize, Deserialize)]").unwrap();
-    writeln!(mod_file, "#[serde(untagged)]").unwrap();
-    writeln!(mod_file, "pub enum FormattedValue {{").unwrap();
-    
-    for f in formats.iter() {
-        writeln!(mod_file, "    {},", f).unwrap();
+    if failed {
+        std::process::exit(1);
     }
 
-    writeln!(mod_file, "}}").unwrap();
+    if let Some(out) = &args.out {
+        check_crate(&args.package.builder(&args.schema, out), out);
+    }
+}
 
-    writeln!(mod_file, "\n\nimpl Display for FormattedValue {{").unwrap();
-    writeln!(mod_file, "    fn fmt(&self, f: &mut Formatter) -> FmtResult {{").unwrap();
-    writeln!(mod_file, "        match self {{").unwrap();
-    
-    // Format names are Enums with inner string EnumName(String)
-    let format_names = formats.into_iter().map(|s|s.split_once('(').unwrap().0.to_string()).collect::<Vec<String>>();
+// Compare existing crate with freshly generated one
+fn check_crate(builder: &Builder, out: &Path) {
+    let differences = match builder.diff() {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Failed to generate library for comparison with {:?}: {}", out, e);
+            std::process::exit(1);
+        }
+    };
 
-    for i in format_names.iter() {
-        writeln!(mod_file, "            Self::{}(v) => write!(f, \"{{}}\", v),", i).unwrap();
+    if differences.is_empty() {
+        println!("ok     {} is up to date", out.display());
+        return
     }
 
-    writeln!(mod_file, "        }}").unwrap();
-    writeln!(mod_file, "    }}").unwrap();
-    writeln!(mod_file, "}}").unwrap();
+    for d in differences.iter() {
+        print!("{}", d.diff);
+    }
+
+    for d in differences.iter() {
+        let kind = match d.kind {
+            DifferenceKind::Missing => "missing",
+            DifferenceKind::Changed => "changed",
+            DifferenceKind::Stale => "stale",
+        };
 
+        println!("{:<8}{}", kind, out.join(&d.path).display());
+    }
 
-    // Implement init method for FormattedValue
-    writeln!(mod_file, "\n\nimpl FormattedValue {{").unwrap();
+    println!("failed {} is out of date, {} files differ", out.display(), differences.len());
+    std::process::exit(1);
+}
 
-    for i in format_names.iter() {
-        // Skip Date, Time, DateTime, since these are handled by chrono
-        if ["Date", "Time", "DateTime"].contains(&i.as_str()) {
-            continue;
+fn inspect(args: &InspectArgs) {
+    let builder = args.schema.builder();
+
+    for in_path in schema_files(&builder) {
+        let schema_file = in_path.display();
+
+        let (definitions, root_properties) = match read_definitions(&in_path) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("{}: {}", schema_file, e);
+                std::process::exit(1);
+            }
+        };
+
+        let mut kinds = vec![];
+
+        for (k, v) in definitions.iter() {
+            let is_choice = v.get("oneOf").or(v.get("anyOf")).is_some();
+
+            let kind = match (v.get("type").and_then(|v|v.as_str()), v.get("properties"), v.get("$ref")) {
+                _ if v.get("allOf").is_some() => "component",
+                _ if is_choice => "choice",
+                (Some("object"), Some(_), _) => "component",
+                (Some("object"), None, _) => "extension",
+                (_, _, Some(_)) => "reference",
+                _ => "unsupported",
+            };
+
+            kinds.push((k, kind));
         }
 
-        let snaked = snaker(i);
-        writeln!(mod_file, "    pub fn new_{}<T>(v: T) -> Self where T: Into<String> {{", snaked).unwrap();
-        writeln!(mod_file, "        Self::{}(v.into())", i).unwrap();
-        writeln!(mod_file, "    }}").unwrap();
+        let count = |kind: &str| kinds.iter().filter(|(_, k)|k.eq(&kind)).count();
+
+        println!("{} (mod {})", schema_file, builder.module_name(&in_path));
+        println!("  components:  {}", count("component"));
+        println!("  choices:     {}", count("choice"));
+        println!("  references:  {}", count("reference"));
+        println!("  extensions:  {}", count("extension"));
+        println!("  unsupported: {}", count("unsupported"));
+
+        // Maindoc schemas have the document in root level properties
+        if !root_properties.is_empty() {
+            println!("  document:    {}", root_properties.join(", "));
+        }
+
+        if args.definitions {
+            for (k, kind) in kinds {
+                println!("    {} ({})", k, kind);
+            }
+        }
     }
+}
 
-    // Implement new Date, Time, DateTime methods
-    writeln!(mod_file, "    pub fn new_datetime(v: chrono::NaiveDateTime) -> Self {{
-        Self::DateTime(v.format(\"%Y-%m-%dT%H:%M:%S\").to_string())
-    }}
-    /// Create new date time from a string formatted as `YYYY-MM-DD HH:MM:SS`
-    pub fn new_date_time_from_str<T>(v: T) -> Result<Self, UblError> where T: AsRef<str> {{
-        let s = v.as_ref();
-        let fmt = \"%Y-%m-%d %H:%M:%S\";
-        chrono::NaiveDateTime::parse_from_str(s, fmt)
-            .map(|n|Self::DateTime(n.format(\"%Y-%m-%dT%H:%M:%S\").to_string()))
-            .map_err(|e|UblError::date_time(s, fmt, e))
-    }}
-    /// Create new date time from a string with custom formatting (timezone, fractional seconds, etc.)
-    /// Be advised that the formatted value is stored as string in format `YYYY-MM-DDTHH:MM:SS`
-    pub fn new_date_time_from_str_in_fmt<T, F>(v: T, format: F) -> Result<Self, UblError> where T: AsRef<str>, F: AsRef<str> {{
-        let s = v.as_ref();
-        let fmt_in = format.as_ref();
-        let fmt_out = \"%Y-%m-%dT%H:%M:%S\";
-        chrono::NaiveDateTime::parse_from_str(s, fmt_in)
-            .map(|n|Self::DateTime(n.format(fmt_out).to_string()))
-            .map_err(|e|UblError::date_time(s, fmt_out, e))
-    }}
-    pub fn new_date(date: chrono::NaiveDate) -> Self {{
-        Self::Date(date.format(\"%Y-%m-%d\").to_string())
-    }}
-    pub fn new_date_from_str<T>(v: T) -> Result<Self, UblError> where T: AsRef<str> {{
-        let s = v.as_ref();
-        let fmt = \"%Y-%m-%d\";
-        chrono::NaiveDate::parse_from_str(s, fmt)
-            .map(|n|Self::Date(n.format(fmt).to_string()))
-            .map_err(|e|UblError::date_time(s, fmt, e))
-    }}
-    pub fn new_date_from_str_in_fmt<T, F>(v: T, format: F) -> Result<Self, UblError> where T: AsRef<str>, F: AsRef<str> {{
-        let s = v.as_ref();
-        let fmt_in = format.as_ref();
-        let fmt_out = \"%Y-%m-%d\";
-        chrono::NaiveDate::parse_from_str(s, fmt_in)
-            .map(|n|Self::Date(n.format(fmt_out).to_string()))
-            .map_err(|e|UblError::date_time(s, fmt_out, e))
-    }}
-    pub fn new_time(time: chrono::NaiveTime) -> Self {{
-        Self::Time(time.format(\"%H:%M:%S\").to_string())
-    }}
-    pub fn new_time_from_str<T>(v: T) -> Result<Self, UblError> where T: AsRef<str> {{
-        let s = v.as_ref();
-        let fmt = \"%H:%M:%S\";
-        chrono::NaiveTime::parse_from_str(s, fmt)
-            .map(|n|Self::Time(n.format(fmt).to_string()))
-            .map_err(|e|UblError::date_time(s, fmt, e))
-    }}
-    pub fn new_time_from_str_in_fmt<T, F>(v: T, format: F) -> Result<Self, UblError> where T: AsRef<str>, F: AsRef<str> {{
-        let s = v.as_ref();
-        let fmt_in = format.as_ref();
-        let fmt_out = \"%H:%M:%S\";
-        chrono::NaiveTime::parse_from_str(s, fmt_in)
-            .map(|n|Self::Time(n.format(fmt_out).to_string()))
-            .map_err(|e|UblError::date_time(s, fmt_out, e))
-    }}").unwrap();
-
-    writeln!(mod_file, "}}").unwrap();
-
-
-    for (refs_file, (_referenced_path, comp_ref)) in post_processables {
-        let mut out_file = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&refs_file)
-            .expect("Failed to open mod reference file");
-
-        // println!("comp ref > [{}]: {:?}", referenced_path.exists(), referenced_path);
-
-        writeln!(out_file, "{}",comp_ref).unwrap();
+fn schema_files(builder: &Builder) -> Vec<PathBuf> {
+    match builder.schema_files() {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Failed to resolve schema files: {}", e);
+            std::process::exit(1);
+        }
     }
+}
+
+// Definitions and root level property names of the schema
+fn read_definitions(in_path: &Path) -> Result<(serde_json::Map<String, serde_json::Value>, Vec<String>), String> {
+    let contents = std::fs::read_to_string(in_path)
+        .map_err(|e|format!("unable to read file: {}", e))?;
+
+    let mut value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e|format!("unable to parse JSON: {}", e))?;
+
+    let root_properties = value.get("properties")
+        .and_then(|v|v.as_object())
+        .map(|o|o.keys().cloned().collect())
+        .unwrap_or_default();
 
-    // Check if exporter.rs file is present in schema/src directory
-    let exporter_rs = PathBuf::from("../ubl-rs/src/exporter.rs");
-    
-    // Create empty file if missing
-    if !exporter_rs.exists() {
-        std::fs::File::create(&exporter_rs)
-            .expect("Failed to create exporter.rs file to schema lib");
+    match value.get_mut("definitions").map(|v|v.take()) {
+        Some(serde_json::Value::Object(m)) => Ok((m, root_properties)),
+        Some(_) => Err(String::from("definitions is not an object")),
+        None => Err(String::from("no definitions")),
     }
 }
@@ -0,0 +1,256 @@
+use std::fs::{create_dir_all, read_to_string, remove_dir_all, remove_file, rename, write};
+use std::io::Result;
+use std::path::{Component, Path, PathBuf};
+
+
+// Output is written to staging directory first and generated entries are then swapped in with
+// renames, so a failure while generating leaves the existing output as it was. Staging and
+// backup directories are created inside the output directory to keep the renames on the
+// same file system. Entries not generated by the builder, like exporter.rs, are never touched.
+// Generated entries are listed in entries file, so that entries of earlier runs that are no
+// longer generated (e.g. modules of removed schemas) can be removed.
+
+const STAGING_PREFIX: &str = ".ubl-rs-builder-";
+
+/// File in the output directory listing the generated entries, one path per line
+pub const ENTRIES_FILE: &str = ".ubl-rs-entries";
+
+/// Temporary directory for writing the output before it's installed
+pub fn staging_dir(out_path: &Path) -> Result<tempfile::TempDir> {
+    create_dir_all(out_path)?;
+
+    tempfile::Builder::new()
+        .prefix(STAGING_PREFIX)
+        .tempdir_in(out_path)
+}
+
+/// Replace entries of the output directory with the staged ones and remove generated entries of
+/// the previous run that are not in `generated` anymore. Entries in `created` are installed but
+/// not listed, they belong to the user once they exist. Existing entries are moved aside and
+/// restored if any of the renames fails.
+pub fn install(staging: &Path, out_path: &Path, generated: &[PathBuf], created: &[PathBuf]) -> Result<()> {
+    let stale = previous_entries(out_path)?
+        .into_iter()
+        .filter(|e|!generated.contains(e) && !created.contains(e))
+        .collect::<Vec<PathBuf>>();
+
+    let listed = generated.iter().map(|e|format!("{}\n", e.display())).collect::<String>();
+    write(staging.join(ENTRIES_FILE), listed)?;
+
+    let entries_file = PathBuf::from(ENTRIES_FILE);
+    let entries = generated.iter().chain(created).chain([&entries_file]);
+
+    let backup = staging_dir(out_path)?;
+    let mut installed = vec![];
+    let mut removed = vec![];
+
+    let result = (||{
+        for entry in stale.iter() {
+            if set_aside(out_path, backup.path(), entry)? {
+                removed.push(entry);
+            }
+        }
+
+        for entry in entries {
+            installed.push((entry, swap(staging, out_path, backup.path(), entry)?));
+        }
+
+        Ok(())
+    })();
+
+    // Put back whatever was already swapped or removed, in reverse order
+    if let Err(e) = result {
+        for (entry, replaced) in installed.into_iter().rev() {
+            restore(out_path, backup.path(), entry, replaced).ok();
+        }
+
+        for entry in removed.into_iter().rev() {
+            rename(backup.path().join(entry), out_path.join(entry)).ok();
+        }
+
+        return Err(e)
+    }
+
+    Ok(())
+}
+
+// Entries listed by the previous run, paths that could point outside the output are ignored
+fn previous_entries(out_path: &Path) -> Result<Vec<PathBuf>> {
+    let file = out_path.join(ENTRIES_FILE);
+
+    if !file.is_file() {
+        return Ok(vec![])
+    }
+
+    Ok(read_to_string(file)?
+        .lines()
+        .map(PathBuf::from)
+        .filter(|p|p.components().next().is_some() && p.components().all(|c|matches!(c, Component::Normal(_))))
+        .collect())
+}
+
+// Move existing entry to backup, true when there was one
+fn set_aside(out_path: &Path, backup: &Path, entry: &Path) -> Result<bool> {
+    let target = out_path.join(entry);
+
+    if target.symlink_metadata().is_err() {
+        return Ok(false)
+    }
+
+    let backup_entry = backup.join(entry);
+
+    if let Some(parent) = backup_entry.parent() {
+        create_dir_all(parent)?;
+    }
+
+    rename(&target, &backup_entry)?;
+    Ok(true)
+}
+
+// Move existing entry to backup and staged one in its place, true when existing was replaced
+fn swap(staging: &Path, out_path: &Path, backup: &Path, entry: &Path) -> Result<bool> {
+    let target = out_path.join(entry);
+
+    if let Some(parent) = target.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let replaced = set_aside(out_path, backup, entry)?;
+
+    if let Err(e) = rename(staging.join(entry), &target) {
+        if replaced {
+            rename(backup.join(entry), &target).ok();
+        }
+
+        return Err(e)
+    }
+
+    Ok(replaced)
+}
+
+// Remove installed entry and move the replaced one back from backup
+fn restore(out_path: &Path, backup: &Path, entry: &Path, replaced: bool) -> Result<()> {
+    let target = out_path.join(entry);
+
+    match target.is_dir() {
+        true => remove_dir_all(&target)?,
+        false => remove_file(&target)?,
+    }
+
+    if replaced {
+        rename(backup.join(entry), &target)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_install_restores_previous_output() {
+        let out = tempfile::tempdir().unwrap();
+        let out_path = out.path();
+
+        create_dir_all(out_path.join("mod_a")).unwrap();
+        write(out_path.join("lib.rs"), "old lib").unwrap();
+        write(out_path.join("mod_a/mod.rs"), "old mod").unwrap();
+        write(out_path.join("exporter.rs"), "exporter").unwrap();
+
+        // Last entry is missing from staging, so its rename fails after the others are swapped
+        let staging = staging_dir(out_path).unwrap();
+        create_dir_all(staging.path().join("mod_a")).unwrap();
+        write(staging.path().join("lib.rs"), "new lib").unwrap();
+        write(staging.path().join("mod_a/mod.rs"), "new mod").unwrap();
+        write(staging.path().join("mod_b.rs"), "new mod").unwrap();
+
+        let entries = ["lib.rs", "mod_a", "mod_b.rs", "mod_c.rs"].map(PathBuf::from);
+        assert!(install(staging.path(), out_path, &entries, &[]).is_err());
+
+        assert_eq!(read_to_string(out_path.join("lib.rs")).unwrap(), "old lib");
+        assert_eq!(read_to_string(out_path.join("mod_a/mod.rs")).unwrap(), "old mod");
+        assert_eq!(read_to_string(out_path.join("exporter.rs")).unwrap(), "exporter");
+        assert!(!out_path.join("mod_b.rs").exists());
+        assert!(!out_path.join("mod_c.rs").exists());
+    }
+
+    #[test]
+    fn install_replaces_entries() {
+        let out = tempfile::tempdir().unwrap();
+        let out_path = out.path();
+
+        write(out_path.join("lib.rs"), "old lib").unwrap();
+        write(out_path.join("exporter.rs"), "exporter").unwrap();
+
+        let staging = staging_dir(out_path).unwrap();
+        write(staging.path().join("lib.rs"), "new lib").unwrap();
+
+        install(staging.path(), out_path, &[PathBuf::from("lib.rs")], &[]).unwrap();
+
+        assert_eq!(read_to_string(out_path.join("lib.rs")).unwrap(), "new lib");
+        assert_eq!(read_to_string(out_path.join("exporter.rs")).unwrap(), "exporter");
+    }
+
+    // Install entries with given contents, directories are given with their `mod.rs`
+    fn install_files(out_path: &Path, files: &[(&str, &str)], created: &[&str]) -> Result<()> {
+        let staging = staging_dir(out_path)?;
+        let mut entries = vec![];
+
+        for (file, contents) in files {
+            let path = staging.path().join(file);
+            create_dir_all(path.parent().unwrap())?;
+            write(path, contents)?;
+
+            let entry = Path::new(file).components().next().unwrap().as_os_str();
+            entries.push(PathBuf::from(entry));
+        }
+
+        let created = created.iter().map(PathBuf::from).collect::<Vec<PathBuf>>();
+        entries.retain(|e|!created.contains(e));
+        entries.dedup();
+
+        install(staging.path(), out_path, &entries, &created)
+    }
+
+    #[test]
+    fn entries_no_longer_generated_are_removed() {
+        let out = tempfile::tempdir().unwrap();
+        let out_path = out.path();
+
+        install_files(out_path, &[
+            ("lib.rs", "old lib"),
+            ("mod_a/mod.rs", "old mod"),
+            ("code_lists/mod.rs", "old codes"),
+            ("exporter.rs", ""),
+        ], &["exporter.rs"]).unwrap();
+
+        write(out_path.join("exporter.rs"), "exporter").unwrap();
+        install_files(out_path, &[("lib.rs", "new lib")], &[]).unwrap();
+
+        assert_eq!(read_to_string(out_path.join("lib.rs")).unwrap(), "new lib");
+        assert_eq!(read_to_string(out_path.join("exporter.rs")).unwrap(), "exporter");
+        assert!(!out_path.join("mod_a").exists());
+        assert!(!out_path.join("code_lists").exists());
+        assert_eq!(read_to_string(out_path.join(ENTRIES_FILE)).unwrap(), "lib.rs\n");
+    }
+
+    #[test]
+    fn failed_install_restores_entries_no_longer_generated() {
+        let out = tempfile::tempdir().unwrap();
+        let out_path = out.path();
+
+        install_files(out_path, &[("lib.rs", "old lib"), ("mod_a/mod.rs", "old mod")], &[]).unwrap();
+
+        // Stale `mod_a` is moved aside before the failing rename of missing `mod_b.rs`
+        let staging = staging_dir(out_path).unwrap();
+        write(staging.path().join("lib.rs"), "new lib").unwrap();
+
+        let entries = ["lib.rs", "mod_b.rs"].map(PathBuf::from);
+        assert!(install(staging.path(), out_path, &entries, &[]).is_err());
+
+        assert_eq!(read_to_string(out_path.join("lib.rs")).unwrap(), "old lib");
+        assert_eq!(read_to_string(out_path.join("mod_a/mod.rs")).unwrap(), "old mod");
+        assert_eq!(read_to_string(out_path.join(ENTRIES_FILE)).unwrap(), "lib.rs\nmod_a\n");
+    }
+}
@@ -1,9 +1,11 @@
 use serde_json::Value;
-use std::fs::File;
-use std::io::Write;
+use std::collections::{HashMap, HashSet};
 
-use super::reference::ref_path_splitter;
-use super::struct_namer;
+use crate::error::SchemaError;
+use crate::ir::{Array, Choice, Component, FieldKind, Module, SingleItem, TypeRef, VariantKind};
+
+use super::reference::type_ref_parser;
+use super::{struct_namer, Context, Pointer};
 
 // Array references have "items" object with "$ref" key. Parent object has "type" key with value "array"
 // and optional title and description.
@@ -19,119 +21,196 @@ use super::struct_namer;
 //     "type": "array"
 //     },
 
-pub fn array_struct_field_writer(parent_key: &String, parent_mod: &str, key: &str,
-val: &Value, snake_key: &String, out_file: &mut File, required: &Vec<&str>,
-impl_buffer: &mut Vec<String>) -> String {
+pub fn array_parser(parent_key: &str, key: &str, val: &Value, at: &Pointer, ctx: &Context) -> Result<Array, SchemaError> {
     let item_key = format!("{}ArrayOf{}Component", parent_key, key);
-    let item_struct = struct_namer(item_key);
-
-    match required.contains(&key) {
-        true => {
-            writeln!(out_file, "    pub {}: {},", snake_key, item_struct).unwrap()
-        },
-        false => {
-            writeln!(out_file, "    #[serde(skip_serializing_if = \"Option::is_none\")]").unwrap();    
-            writeln!(out_file, "    pub {}: Option<{}>,", snake_key, item_struct).unwrap()
-        },
+
+    let items_at = at.join("items");
+    let items = at.get(val, "items")?;
+
+    // Items of UBL arrays are always a reference to a single component
+    if items_at.as_object(items)?.len() != 1 {
+        return Err(items_at.error("expected object with single `$ref` key, items holding more than a reference are not supported"))
     }
 
-    let items = val.get("items")
-        .expect("No items in array type component")
-        .as_object()
-        .expect("Items is not an object");
+    let ref_path = items_at.get(items, "$ref")
+        .and_then(|v|items_at.join("$ref").as_str(v))?;
+
+    let count_at = |k: &str| val.get(k).map(|v|at.join(k).as_u64(v)).transpose();
+
+    Ok(Array {
+        name: struct_namer(item_key),
+        item: type_ref_parser(ref_path, &items_at.join("$ref"), ctx)?,
+        min_items: count_at("minItems")?,
+        max_items: count_at("maxItems")?,
+        single: None,
+    })
+}
 
-    // TODO: is this always the case? Items is always a reference to a single component?
-    if items.len() != 1 {
-        panic!("Items is not an object with one key. Changed since last spec. Can items hold more than one reference?");
+/// Mark arrays with `maxItems: 1` to be generated as their item. Required arrays need `minItems`
+/// too, as the item has to be there. Wrapper's `Vec` used to keep the items on heap, so items
+/// that are structs are boxed. Without it aggregates like `InvoiceLine` hold every nested
+/// aggregate inline and grow to hundreds of kilobytes. Other items are boxed when they lead
+/// back to the struct holding them.
+pub fn single_item_linker(modules: &mut [Module]) {
+    let mut graph = Graph::new();
+    let mut structs = HashSet::new();
+    let mut references = HashMap::new();
+
+    for module in modules.iter_mut() {
+        for comp in module.components.iter_mut() {
+            structs.insert(node(&module.name, &comp.name));
+            component_linker(comp, &module.name, &mut graph);
+        }
+
+        for choice in module.choices.iter_mut() {
+            choice_linker(choice, &module.name, &mut graph);
+        }
+
+        for r in module.references.iter() {
+            let (from, to) = (node(&module.name, &r.name), target_node(&r.target, &module.name));
+
+            graph.entry(from.clone()).or_default().push(to.clone());
+            references.insert(from, to);
+        }
     }
 
-    let ref_path = items.get("$ref")
-        .expect("No reference in items")
-        .as_str()
-        .expect("Reference is not a string");
-
-    let (subdir, _, ref_struct_name) = ref_path_splitter(ref_path);
-
-    // Add struct definition
-    let mut vals = vec![String::from("#[derive(Debug, Clone, Deserialize, Serialize)]")];
-    // Items should not be displayed on the serialized json
-    vals.push(format!("#[serde(transparent)]\npub struct {} {{", item_struct));
-
-    // Determine path to referenced struct
-    let ref_mod_trail = match subdir {
-        Some(s) => format!("crate::{}::{}", s, ref_struct_name),
-        None => format!("crate::{}::{}", parent_mod, ref_struct_name),
-    };
-    
-    vals.push(format!("    pub items: Vec<{}>,", ref_mod_trail));
-
-    // Implement AsMut for component struct
-    vals.push(format!("}}\n\nimpl AsMut<{}> for {} {{", item_struct, item_struct));
-    vals.push(String::from("    fn as_mut(&mut self) -> &mut Self {"));
-    vals.push(String::from("        self"));
-    vals.push(String::from("    }"));
-
-    // Add Componentable trait implementation for struct
-    vals.push(format!("}}\n\nimpl Componentable<{}> for {} {{", item_struct, item_struct));
-    vals.push(String::from("    fn validate(&self) -> Result<&Self, UblError> {"));
-
-    if val.get("maxItems").is_some() || val.get("minItems").is_some() {
-        vals.push(format!("        let len = self.items.len();\n"));
+    let linker = BoxLinker { graph, structs, references };
+
+    for module in modules.iter_mut() {
+        for comp in module.components.iter_mut() {
+            linker.component(comp, &module.name);
+        }
+
+        for choice in module.choices.iter_mut() {
+            linker.choice(choice, &module.name);
+        }
     }
+}
 
-    if let Some(v) = val.get("maxItems") {
-        let u = v.as_u64().expect("maxItems is not a number");
+// Structs and enums by module and name, pointing to the ones they hold by value
+type Graph = HashMap<(String, String), Vec<(String, String)>>;
 
-        vals.push(format!("        if len > {} {{", u));
-        vals.push(format!("            return Err(UblError::inner_component(\"{}\", format!(\"Max allowed items is {} and you have {{}}\", len)))", item_struct, u));
-        vals.push(format!("        }}"));
+fn node(module: &str, name: &str) -> (String, String) {
+    (module.to_owned(), name.to_owned())
+}
+
+fn target_node(target: &TypeRef, module: &str) -> (String, String) {
+    node(target.module.as_deref().unwrap_or(module), &target.name)
+}
+
+// Mark single items of component and its nested structs, collect what they hold by value
+fn component_linker(comp: &mut Component, module: &str, graph: &mut Graph) {
+    let from = node(module, &comp.name);
+
+    for field in comp.fields.iter_mut() {
+        match &mut field.kind {
+            FieldKind::Array(a) if a.max_items == Some(1) && (!field.required || a.min_items.is_some_and(|m|m > 0)) => {
+                a.single = Some(SingleItem::Item);
+                graph.entry(from.clone()).or_default().push(target_node(&a.item, module));
+            },
+            FieldKind::Object(c) => {
+                graph.entry(from.clone()).or_default().push(node(module, &c.name));
+                component_linker(c, module, graph);
+            },
+            FieldKind::Choice(c) => {
+                graph.entry(from.clone()).or_default().push(node(module, &c.name));
+                choice_linker(c, module, graph);
+            },
+            _ => (),
+        }
     }
-    
-    if let Some(v) = val.get("minItems") {
-        let u = v.as_u64().expect("minItems is not a number");
+}
 
-        vals.push(format!("        if len < {} {{", u));
-        vals.push(format!("            return Err(UblError::inner_component(\"{}\", format!(\"Min allowed items is {} and you have {{}}\", len)))", item_struct, u));
-        vals.push(format!("        }}"));
+fn choice_linker(choice: &mut Choice, module: &str, graph: &mut Graph) {
+    let from = node(module, &choice.name);
+
+    for variant in choice.variants.iter_mut() {
+        match &mut variant.kind {
+            VariantKind::Ref { target, .. } => {
+                graph.entry(from.clone()).or_default().push(target_node(target, module));
+            },
+            VariantKind::Object(c) => {
+                graph.entry(from.clone()).or_default().push(node(module, &c.name));
+                component_linker(c, module, graph);
+            },
+            _ => (),
+        }
     }
+}
 
-    vals.push(format!("\n        Ok(self)"));
-    vals.push(format!("    }}"));
+// Decides which single items are boxed
+struct BoxLinker {
+    graph: Graph,
+    /// Components by module and name
+    structs: HashSet<(String, String)>,
+    /// References pointing to their target
+    references: HashMap<(String, String), (String, String)>,
+}
 
-    vals.push(String::from("    fn get(self) -> Result<Self, UblError> {"));
-    vals.push(String::from("        self.validate().map(|s|s.clone())"));
-    vals.push(String::from("    }"));
+impl BoxLinker {
+    fn component(&self, comp: &mut Component, module: &str) {
+        let from = node(module, &comp.name);
+
+        for field in comp.fields.iter_mut() {
+            match &mut field.kind {
+                FieldKind::Array(a) if a.single.is_some() => {
+                    let target = target_node(&a.item, module);
+
+                    if self.is_struct(&target) || reaches(&self.graph, target, &from) {
+                        a.single = Some(SingleItem::Boxed);
+                    }
+                },
+                FieldKind::Object(c) => self.component(c, module),
+                FieldKind::Choice(c) => self.choice(c, module),
+                _ => (),
+            }
+        }
+    }
 
-    vals.push(String::from("    fn additional_props_allowed() -> bool {"));
-    vals.push(String::from("        false"));
-    vals.push(String::from("    }"));
+    fn choice(&self, choice: &mut Choice, module: &str) {
+        for variant in choice.variants.iter_mut() {
+            if let VariantKind::Object(c) = &mut variant.kind {
+                self.component(c, module);
+            }
+        }
+    }
 
-    // Add struct implementation
-    vals.push(format!("}}\n\nimpl {} {{", item_struct));
+    // Whether the node is a component or reference resolving to one
+    fn is_struct(&self, node: &(String, String)) -> bool {
+        let mut current = node;
+        let mut visited = HashSet::new();
 
-    // Methods for: initializing new struct with one item
-    vals.push(format!("    pub fn new(item: {}) -> Component<Self> {{", ref_mod_trail));
-    vals.push(format!("        Component(Self {{
-            items: vec![item],
-        }})
-    }}"));
+        while visited.insert(current) {
+            if self.structs.contains(current) {
+                return true
+            }
 
-    // ..pushing new item to existing struct
-    vals.push(format!("    pub fn push(&mut self, item: {}) {{", ref_mod_trail));
-    vals.push(format!("        self.items.push(item);
-    }}"));
+            match self.references.get(current) {
+                Some(target) => current = target,
+                None => return false,
+            }
+        }
 
-    // iterating mutably over added items
-    vals.push(format!("    pub fn iter_mut(&mut self) -> std::slice::IterMut<{}> {{", ref_mod_trail));
-    vals.push(format!("        self.items.iter_mut()
-    }}"));
+        false
+    }
+}
+
+// Depth first search from start node to target
+fn reaches(graph: &Graph, start: (String, String), target: &(String, String)) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack = vec![start];
 
-    // iterating over added items
-    vals.push(format!("    pub fn iter(&self) -> std::slice::Iter<{}> {{", ref_mod_trail));
-    vals.push(format!("        self.items.iter()
-    }}\n}}\n"));
+    while let Some(n) = stack.pop() {
+        if n.eq(target) {
+            return true
+        }
 
-    impl_buffer.extend(vals);
+        if let Some(next) = graph.get(&n) {
+            stack.extend(next.iter().filter(|m|!visited.contains(*m)).cloned());
+        }
+
+        visited.insert(n);
+    }
 
-    item_struct
+    false
 }
@@ -0,0 +1,166 @@
+use roxmltree::Node;
+use std::path::{Path, PathBuf};
+
+use crate::error::BuilderError;
+use crate::ir::{Code, CodeList, Module};
+
+use super::enum_namer;
+
+
+// Genericode files identify the code list in "Identification" and have the codes as rows of
+// "SimpleCodeList". Row has a value per column, the column referenced from "Key" holds the code.
+// Elements inside the root are not namespaced.
+// Example of code list:
+// <gc:CodeList xmlns:gc="http://docs.oasis-open.org/codelist/ns/genericode/1.0/">
+//   <Identification>
+//     <ShortName>CurrencyCode</ShortName>
+//     <LongName xml:lang="en">Currency</LongName>
+//     <Version>2001</Version>
+//     <CanonicalUri>urn:un:unece:uncefact:codelist:specification:54217</CanonicalUri>
+//   </Identification>
+//   <ColumnSet>
+//     <Column Id="code" Use="required"><ShortName>Code</ShortName></Column>
+//     <Column Id="name" Use="optional"><ShortName>Name</ShortName></Column>
+//     <Key Id="codeKey"><ShortName>CodeKey</ShortName><ColumnRef Ref="code"/></Key>
+//   </ColumnSet>
+//   <SimpleCodeList>
+//     <Row>
+//       <Value ColumnRef="code"><SimpleValue>EUR</SimpleValue></Value>
+//       <Value ColumnRef="name"><SimpleValue>Euro</SimpleValue></Value>
+//     </Row>
+//   </SimpleCodeList>
+// </gc:CodeList>
+
+// Variant names that can't come from codes, `Other` holds the codes not in the list
+const RESERVED_VARIANTS: [&str; 2] = ["Other", "Self"];
+
+/// Genericode `.gc` files in code list directory, sorted by file name
+pub fn code_list_files(dir: &Path) -> Result<Vec<PathBuf>, BuilderError> {
+    let read_err = |e: std::io::Error| BuilderError::CodeList { file: dir.to_path_buf(), reason: e.to_string() };
+    let mut files = vec![];
+
+    for entry in std::fs::read_dir(dir).map_err(read_err)? {
+        let path = entry.map_err(read_err)?.path();
+
+        if path.is_file() && path.extension().is_some_and(|e|e.eq("gc")) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Read Genericode file into code list
+pub fn code_list_parser(file: &Path) -> Result<CodeList, BuilderError> {
+    let error = |reason: String| BuilderError::CodeList { file: file.to_path_buf(), reason };
+
+    let contents = std::fs::read_to_string(file).map_err(|e|error(e.to_string()))?;
+    let doc = roxmltree::Document::parse(&contents).map_err(|e|error(e.to_string()))?;
+    let root = doc.root_element();
+
+    let identification = child(root, "Identification")
+        .ok_or_else(||error(String::from("missing `Identification`")))?;
+
+    let short_name = child_text(identification, "ShortName")
+        .ok_or_else(||error(String::from("missing `Identification/ShortName`")))?;
+
+    let columns = child(root, "ColumnSet")
+        .ok_or_else(||error(String::from("missing `ColumnSet`")))?;
+
+    // Code is in the key column, name and description are optional columns
+    let code_column = child(columns, "Key")
+        .and_then(|k|child(k, "ColumnRef"))
+        .and_then(|c|c.attribute("Ref"))
+        .unwrap_or("code");
+
+    let name_column = column_id(columns, "name");
+    let descr_column = column_id(columns, "description");
+
+    let rows = child(root, "SimpleCodeList")
+        .ok_or_else(||error(String::from("missing `SimpleCodeList`")))?
+        .children()
+        .filter(|n|n.has_tag_name("Row"));
+
+    let mut codes: Vec<Code> = vec![];
+
+    for (i, row) in rows.enumerate() {
+        let value = row_value(row, code_column)
+            .ok_or_else(||error(format!("row {} has no value for key column `{}`", i + 1, code_column)))?;
+
+        // Same code can't be listed twice, but every code needs an unique variant
+        if codes.iter().any(|c|c.value.eq(&value)) {
+            return Err(error(format!("code `{}` is listed more than once", value)))
+        }
+
+        let mut variant = code_variant_namer(&value);
+
+        if RESERVED_VARIANTS.contains(&variant.as_str()) || codes.iter().any(|c|c.variant.eq(&variant)) {
+            variant = format!("{}{}", variant, i + 1);
+        }
+
+        codes.push(Code {
+            value,
+            variant,
+            name: name_column.and_then(|c|row_value(row, c)),
+            description: descr_column.and_then(|c|row_value(row, c)),
+        });
+    }
+
+    Ok(CodeList {
+        name: enum_namer(&short_name),
+        short_name,
+        long_name: child_text(identification, "LongName"),
+        version: child_text(identification, "Version"),
+        canonical_uri: child_text(identification, "CanonicalUri"),
+        codes,
+    })
+}
+
+/// Point references named after code lists to their list, e.g. `Currency_CodeType` to `CurrencyCode`
+pub fn code_list_linker(modules: &mut [Module], code_lists: &[CodeList]) {
+    for reference in modules.iter_mut().flat_map(|m|m.references.iter_mut()) {
+        reference.code_list = code_lists.iter()
+            .find(|l|format!("{}Type", l.name).eq(&reference.name))
+            .map(|l|l.name.to_owned());
+    }
+}
+
+// Variant name from code, codes not starting with a letter are prefixed e.g. `Code10`
+fn code_variant_namer(code: &str) -> String {
+    let mut name = code.chars().filter(|c|c.is_ascii_alphanumeric()).collect::<String>();
+
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            name.replace_range(0..1, &c.to_ascii_uppercase().to_string());
+            name
+        },
+        _ => format!("Code{}", name),
+    }
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n|n.is_element() && n.tag_name().name().eq(name))
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name)
+        .and_then(|n|n.text())
+        .map(|t|t.trim().to_owned())
+        .filter(|t|!t.is_empty())
+}
+
+// Id of the column with given id or short name, compared case insensitively
+fn column_id<'a>(columns: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    columns.children()
+        .filter(|n|n.has_tag_name("Column"))
+        .find(|c|c.attribute("Id").is_some_and(|id|id.eq_ignore_ascii_case(name))
+            || child_text(*c, "ShortName").is_some_and(|s|s.eq_ignore_ascii_case(name)))
+        .and_then(|c|c.attribute("Id"))
+}
+
+fn row_value(row: Node, column: &str) -> Option<String> {
+    row.children()
+        .find(|n|n.has_tag_name("Value") && n.attribute("ColumnRef").eq(&Some(column)))
+        .and_then(|v|child_text(v, "SimpleValue"))
+}
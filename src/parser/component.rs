@@ -1,14 +1,12 @@
 use serde_json::Value;
-use std::collections::HashMap;
-use std::fs::{create_dir_all, remove_dir_all, remove_file};
-use std::io::Write;
-use std::path::PathBuf;
 
-use crate::parser::impl_title_and_descr_writer;
+use crate::error::SchemaError;
+use crate::ir::{Component, Constraints, Field, FieldKind, Temporal};
+use crate::report::UnhandledKeyword;
 
-use super::array::array_struct_field_writer;
-use super::extension::extension_writer;
-use super::{enum_namer, snaker, struct_namer};
+use super::array::array_parser;
+use super::composition::{all_of_parser, choice_parser};
+use super::{enum_namer, snaker, struct_namer, Context, Pointer};
 
 // Component object has a "type" key with value "object" and optional title, description and
 // "additionalProperties" key with boolean value.
@@ -52,394 +50,201 @@ use super::{enum_namer, snaker, struct_namer};
 //     "type": "array"
 //     },
 
-const UC_ALPHABETS: [&str; 26] = [
-    "A", "B", "C", "D", "E", "F", "G", "H", "I", "J",
-    "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T",
-    "U", "V", "W", "X", "Y", "Z"
-];
-
-pub fn component_parser(definitions: &Value, out_dir: &PathBuf, parent_mod: &str) -> Vec<String> {
-    let mut comps_dir = out_dir.clone();
-    comps_dir.push("components");
-
-    let mut exts_file_path = out_dir.clone();
-    exts_file_path.push("extensions.rs");
-
-    // Delete old before writing new
-    remove_dir_all(&comps_dir).ok();
-    remove_file(&exts_file_path).ok();
-
-    // Collect all mod names and string formats
-    let mut mods = vec![];
-    let mut formats = vec![];
-    let mut extensions = false;
-
-    create_dir_all(&comps_dir).expect("Failed to create components directory");
-    let mut exts_file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&exts_file_path)
-        .expect(&format!("Failed to open extensions file: {:?}", exts_file_path));
-
-    writeln!(exts_file, "use serde::{{Deserialize, Serialize}};").unwrap();
-
-    for (k, v) in definitions.as_object().expect("Definitions is not an object") {
-        let obj = v.as_object().expect("Object is not an object");
-        let obj_type = obj.get("type").and_then(|v|v.as_str());
-
-        if obj_type != Some("object") {
-            continue;
-        }
-
-        if !obj.contains_key("properties") {
-            extension_writer(&mut exts_file, k);
-            extensions = true;
-            continue;
-        }
-
-        // Required values from root level
-        let properties = obj.get("properties")
-            .unwrap()
-            .as_object()
-            .expect("Properties is not an object");
-
-        //  Optional values from root level
-        let title = obj.get("title").and_then(|v|v.as_str());
-        let description = obj.get("description").and_then(|v|v.as_str());
-        let additional_props = obj.get("additionalProperties")
-            .and_then(|v|v.as_bool())
-            .unwrap_or(false);
-
-        let required = obj.get("required").and_then(|v|
-            Some(v.as_array()
-            .expect("Required is not an array")
-            .into_iter()
-            .map(|v|v.as_str().expect("Required array key is not a string"))
-            .collect::<Vec<&str>>())
-        ).unwrap_or(vec![]);
-
-        let mut comp_file = comps_dir.clone();
-        let filename = snaker(k);
-        
-        comp_file.push(&filename);
-        comp_file.set_extension("rs");
-        mods.push(filename);
-
-        // Open file for writing, but dont overwrite it
-        let mut out_file = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&comp_file)
-            .expect(&format!("Failed to open component file: {:?}", comp_file));
-
-        let comp_struct_name = struct_namer(k);
-        let mut impl_buffer = vec![];
-        let mut validations = HashMap::new();
-        let mut new_instance = HashMap::new();
-
-        writeln!(out_file, "use serde::{{Deserialize, Serialize}};").unwrap();
-        writeln!(out_file, "use crate::{{UblError, Component, Componentable}};").unwrap();
-        writeln!(out_file, "\n\n#[derive(Debug, Clone, Deserialize, Serialize)]").unwrap();
-
-        writeln!(out_file, "pub struct {} {{", comp_struct_name).unwrap();
-
-        // Components come from schema properties
-        for (pk, pv) in properties {
-            // Open component type to see what type of property it is
-            let comp_type = pv.get("type")
-                .expect("No type in property")
-                .as_str()
-                .expect("Type is not a string");
-
-            // Some properties have format key in which case we should use 
-            // FormattedValue enum for this component value
-            let (string_format, format_variant) = match pv.get("format") {
-                Some(v) => {
-                    let format = v.as_str().expect("Format is not a string");
-                    let mut enum_key = enum_namer(format);
-                    
-                    let enum_variant = format!("crate::FormattedValue::{}(s)", enum_key);
-                    enum_key.push_str("(String)");
-                    
-                    if !formats.contains(&enum_key) {
-                        formats.push(enum_key.to_owned());
-                    }
-
-                    ("crate::FormattedValue", Some(enum_variant))
-                },
-                None => ("String", None)
-            };
-
-            let snake_key = snaker(pk);
-            let is_req = required.contains(&pk.as_str());
-
-            // Serialize _uc as lowdash symbol in json
-            if snake_key.eq("_uc") {
-                writeln!(out_file, "    #[serde(rename = \"_\")]").unwrap();
-            }
-            // Other fields have to be serialized as they are in json spec
-            else {
-                writeln!(out_file, "    #[serde(rename = \"{}\")]", pk).unwrap();
-            }
-
-            match comp_type {
-                "string" => {
-                    match is_req {
-                        true => {
-                            writeln!(out_file, "    pub {}: {},", snake_key, string_format).unwrap()
-                        },
-                        false => {
-                            writeln!(out_file, "    #[serde(skip_serializing_if = \"Option::is_none\")]").unwrap();
-                            writeln!(out_file, "    pub {}: Option<{}>,", snake_key, string_format).unwrap()
-                        }
-                    }
-
-                    let val_t = match string_format.eq("String") {
-                        true => ("str", is_req, None),
-                        false => ("fmt", is_req, format_variant),
-                    };
-
-                    new_instance.insert(snake_key.to_owned(), (val_t.0, None, is_req));
-                    validations.insert(snake_key, val_t);
-                },
-                "number" => {
-                    match is_req {
-                        true => {
-                            writeln!(out_file, "    pub {}: serde_json::Number,", snake_key).unwrap()
-                        },
-                        false => {
-                            writeln!(out_file, "    #[serde(skip_serializing_if = \"Option::is_none\")]").unwrap();    
-                            writeln!(out_file, "    pub {}: Option<serde_json::Number>,", snake_key).unwrap()
-                        },
-                    }
-
-                    new_instance.insert(snake_key, ("number", None, is_req));
-                },
-                "boolean" => {
-                    match is_req {
-                        true => writeln!(out_file, "    pub {}: bool,", snake_key).unwrap(),
-                        false => {
-                            writeln!(out_file, "    #[serde(skip_serializing_if = \"Option::is_none\")]").unwrap();    
-                            writeln!(out_file, "    pub {}: Option<bool>,", snake_key).unwrap()
-                        },
-                    }
-                    
-                    new_instance.insert(snake_key, ("boolean", None, is_req));
-                },
-                "array" => {
-                    let item_struct = array_struct_field_writer(k, parent_mod, pk, pv, &snake_key, &mut out_file, &required, &mut impl_buffer);
-                    validations.insert(snake_key.to_owned(), ("obj_instance", is_req, None));
-                    new_instance.insert(snake_key, ("obj_instance", Some(item_struct), is_req));
-                },
-                _ => panic!("Unknown type: {}", comp_type)
-            }
-        }
-
-        // Implement AsMut for component struct
-        writeln!(out_file, "}}\n\nimpl AsMut<{}> for {} {{", comp_struct_name, comp_struct_name).unwrap();
-        writeln!(out_file, "    fn as_mut(&mut self) -> &mut Self {{").unwrap();
-        writeln!(out_file, "        self").unwrap();
-        writeln!(out_file, "    }}").unwrap();
-
-        // Add Componentable trait implementation for struct
-        writeln!(out_file, "}}\n\nimpl Componentable<{}> for {} {{", comp_struct_name, comp_struct_name).unwrap();
-        writeln!(out_file, "    fn validate(&self) -> Result<&Self, UblError> {{").unwrap();
-        
-        for (pk, (pv, is_req, fmt_var)) in validations {
-            match pv {
-                "str" => match is_req {
-                    true => {
-                        writeln!(out_file, "        if self.{}.is_empty() {{", pk).unwrap();
-                        writeln!(out_file, "            return Err(UblError::empty(\"{}.{}\"))", k, pk).unwrap();
-                        writeln!(out_file, "        }}").unwrap();
-                    },
-                    false => {
-                        writeln!(out_file, "        if let Some(v) = &self.{} {{", pk).unwrap();
-                        writeln!(out_file, "            if v.is_empty() {{").unwrap();
-                        writeln!(out_file, "                return Err(UblError::optional_empty(\"{}.{}\"))", k, pk).unwrap();
-                        writeln!(out_file, "            }}").unwrap();
-                        writeln!(out_file, "        }}").unwrap();
-                    }
-                },
-                "fmt" => {
-                    let fmt_var = fmt_var.unwrap();
-
-                    match is_req {
-                        true => {
-                            writeln!(out_file, "        match &self.{} {{", pk).unwrap();
-                            writeln!(out_file, "            {} => if s.is_empty() {{", fmt_var).unwrap();
-                            writeln!(out_file, "                return Err(UblError::empty(\"{}.{}.{}\"))", k, pk, fmt_var).unwrap();
-                            writeln!(out_file, "            }},").unwrap();
-                            writeln!(out_file, "            e => return Err(UblError::format(\"{}.{}\", e))", k, pk).unwrap();
-                            writeln!(out_file, "        }}").unwrap();
-                        },
-                        false => {
-                            writeln!(out_file, "        if let Some(v) = &self.{} {{", pk).unwrap();
-                            writeln!(out_file, "            match v {{").unwrap();
-                            writeln!(out_file, "                {} => if s.is_empty() {{", fmt_var).unwrap();
-                            writeln!(out_file, "                    return Err(UblError::optional_empty(\"{}.{}.{}\"))", k, pk, fmt_var).unwrap();
-                            writeln!(out_file, "                }},").unwrap();
-                            writeln!(out_file, "                e => return Err(UblError::optional_format(\"{}.{}\", e))", k, pk).unwrap();
-                            writeln!(out_file, "            }}").unwrap();
-                            writeln!(out_file, "        }}").unwrap();
-                        }
-                    }
-                },
-                "obj_instance" => match is_req {
-                    true => {
-                        writeln!(out_file, "        if let Err(e) = self.{}.validate() {{", pk).unwrap();
-                        writeln!(out_file, "            return Err(UblError::component(\"{}.{}\", e));", k, pk).unwrap();
-                        writeln!(out_file, "        }}").unwrap();
-                    },
-                    false => {
-                        writeln!(out_file, "        if let Some(v) = &self.{} {{", pk).unwrap();
-                        writeln!(out_file, "            if let Err(e) = v.validate() {{").unwrap();
-                        writeln!(out_file, "                return Err(UblError::optional_component(\"{}.{}\", e));", k, pk).unwrap();
-                        writeln!(out_file, "            }}").unwrap();
-                        writeln!(out_file, "        }}").unwrap();
-                    }
-                },
-                _ => panic!("Unknown validation type: {}", pv)
-            }
-        }
-
-        writeln!(out_file, "\n        Ok(self)").unwrap();
-        writeln!(out_file, "    }}").unwrap();
-
-        writeln!(out_file, "\n    fn get(self) -> Result<Self, UblError> {{").unwrap();
-        writeln!(out_file, "        self.validate().map(|s|s.clone())").unwrap();
-        writeln!(out_file, "    }}").unwrap();
-
-        writeln!(out_file, "    fn additional_props_allowed() -> bool {{").unwrap();
-        writeln!(out_file, "        {}", additional_props).unwrap();
-        writeln!(out_file, "    }}").unwrap();
-
-        // Add struct implementation
-        writeln!(out_file, "}}\n\nimpl {} {{", comp_struct_name).unwrap();
-        impl_title_and_descr_writer(title, description, &mut out_file);
-
-        // Add new instance method, allow strings to come as refereces. Generics from alphabets
-        let mut new_instance_args = vec![];
-        let mut gen_types = HashMap::new();
-
-        for (pk, (pv, fmt_var, is_req)) in new_instance.iter() {
-            if is_req == &false {
-                continue;
-            }
-            
-            match pv {
-                // Allow generics for required string values only to avoid fn new(argument: None::<&tr>, ...)
-                &"str" => match is_req {
-                    true => {
-                        let index = gen_types.len();
-                        
-                        match UC_ALPHABETS.get(index) {
-                            Some(a) => {
-                                gen_types.insert(pk, *a);
-                                new_instance_args.push(format!("{}: {}", pk, a));
-                            },
-                            None => new_instance_args.push(format!("{}: String", pk)),
-                        }
-                    },
-                    false => new_instance_args.push(format!("{}: String", pk)),
-                },
-                &"fmt" => {
-                    new_instance_args.push(format!("{}: crate::FormattedValue", pk));
-                },
-                &"number" => {
-                    new_instance_args.push(format!("{}: serde_json::Number", pk));
-                },
-                &"boolean" => {
-                    new_instance_args.push(format!("{}: bool", pk));
-                },
-                &"obj_instance" => {
-                    new_instance_args.push(format!("{}: {}", pk, fmt_var.clone().unwrap()));
-                },
-                _ => panic!("Unknown new instance type: {}", pv)
-            }
-        }
-
-        // Maintain order of arguments
-        new_instance_args.sort();
-
-        // Allow string values to come in as a reference
-        match gen_types.len() {
-            0 => writeln!(out_file, "    pub fn new({}) -> Component<Self> {{", new_instance_args.join(", ")).unwrap(),
-            _ => writeln!(
-                out_file,
-                "    pub fn new<{}>({}) -> Component<Self> where {} {{",
-                gen_types.values().into_iter().map(|s|s.to_string()).collect::<Vec<String>>().join(", "),
-                new_instance_args.join(", "),
-                gen_types.iter().map(|(_, t)|format!("{}: Into<String>", t)).collect::<Vec<String>>().join(", ")
-            ).unwrap(),
-        }
-
-        writeln!(out_file, "        Component(Self {{").unwrap();
-
-        // For generic string references, convert them to string
-        for (pk, (pv, _, is_req)) in new_instance.iter() {
-            match pv {
-                &"str" => match gen_types.get(pk) {
-                    Some(t) => {
-                        writeln!(out_file, "            {}: {}.into(), // Generic type: {}", pk, pk, t).unwrap();
-                    },
-                    // New method ignores optional arguments and inits instance optional fields as None
-                    None => match is_req {
-                        true => {
-                            writeln!(out_file, "            {},", pk).unwrap();
-                        },
-                        false => {
-                            writeln!(out_file, "            {}: None,", pk).unwrap();
-                        }
-                    }
-                },
-                _ => match is_req {
-                    true => {
-                        writeln!(out_file, "            {},", pk).unwrap();
-                    },
-                    false => {
-                        writeln!(out_file, "            {}: None,", pk).unwrap();
-                    }
-                }
-            }
-        }
-
-        writeln!(out_file, "        }})").unwrap();
-        writeln!(out_file, "    }}").unwrap();
-        writeln!(out_file, "}}\n").unwrap();
-
-        for i in impl_buffer {
-            writeln!(out_file, "{}", i).unwrap();
+const COMPONENT_KEYWORDS: [&str; 6] = ["type", "title", "description", "properties", "required", "additionalProperties"];
+
+// Keywords handled for every property and additionally per property type
+const PROPERTY_KEYWORDS: [&str; 3] = ["type", "title", "description"];
+const STRING_KEYWORDS: [&str; 5] = ["format", "enum", "pattern", "minLength", "maxLength"];
+const NUMBER_KEYWORDS: [&str; 2] = ["minimum", "maximum"];
+const ARRAY_KEYWORDS: [&str; 3] = ["items", "minItems", "maxItems"];
+
+/// Component from object definition, all errors found from its properties are returned
+pub fn component_parser(key: &str, value: &Value, at: &Pointer, ctx: &Context, unhandled: &mut Vec<UnhandledKeyword>)
+-> Result<Component, Vec<SchemaError>> {
+    let mut errors = vec![];
+
+    unhandled.extend(at.unhandled(value, &COMPONENT_KEYWORDS));
+
+    //  Optional values from root level, errors in them don't stop reading the properties
+    let (title, description) = match (at.str_at(value, "title"), at.str_at(value, "description")) {
+        (Ok(t), Ok(d)) => (t.map(String::from), d.map(String::from)),
+        (t, d) => {
+            errors.extend(t.err().into_iter().chain(d.err()));
+            (None, None)
+        },
+    };
+
+    let additional_props = match value.get("additionalProperties").map(|v|at.join("additionalProperties").as_bool(v)) {
+        Some(Ok(a)) => a,
+        Some(Err(e)) => {
+            errors.push(e);
+            false
+        },
+        None => false,
+    };
+
+    let required = match value.get("required").map(|v|required_parser(v, &at.join("required"))) {
+        Some(Ok(r)) => r,
+        Some(Err(e)) => {
+            errors.push(e);
+            vec![]
+        },
+        None => vec![],
+    };
+
+    // Required values from root level
+    let props_at = at.join("properties");
+    let properties = match at.get(value, "properties").and_then(|v|props_at.as_object(v)) {
+        Ok(p) => Some(p),
+        Err(e) => {
+            errors.push(e);
+            None
+        },
+    };
+
+    let mut fields = vec![];
+
+    // Components come from schema properties
+    for (pk, pv) in properties.into_iter().flatten() {
+        match field_parser(key, pk, pv, &props_at.join(pk), &required, ctx, unhandled) {
+            Ok(f) => fields.push(f),
+            Err(e) => errors.extend(e),
         }
     }
 
-    // Delete extensions.rs if its not needed
-    if !extensions {
-        remove_file(&exts_file_path).ok();
+    if !errors.is_empty() {
+        return Err(errors)
     }
 
-    if mods.len() == 0 {
-        remove_dir_all(&comps_dir).ok();
-
-        return formats
-    }
-
-    // Create mod.rs file in components directory
-    let mut mod_file = comps_dir.clone();
-    mod_file.push("mod.rs");
-
-    let mut mod_file = std::fs::File::create(&mod_file)
-        .expect("Failed to create file");
-
-    for m in mods.iter() {
-        writeln!(mod_file, "mod {};", m).unwrap();
-    }
-
-    writeln!(mod_file, "\n").unwrap();
+    Ok(Component {
+        key: key.to_owned(),
+        name: struct_namer(key),
+        title,
+        description,
+        additional_props,
+        fields,
+    })
+}
+
+pub fn required_parser<'v>(value: &'v Value, at: &Pointer) -> Result<Vec<&'v str>, SchemaError> {
+    at.as_array(value)?
+        .iter()
+        .enumerate()
+        .map(|(i, v)|at.join(i.to_string()).as_str(v))
+        .collect()
+}
+
+pub fn field_parser(comp_key: &str, key: &str, value: &Value, at: &Pointer, required: &[&str], ctx: &Context,
+unhandled: &mut Vec<UnhandledKeyword>) -> Result<Field, Vec<SchemaError>> {
+    // Inline objects and compositions are nested types named after their parent, e.g. `AmountTypeDetails`
+    let nested_key = format!("{}{}", comp_key, enum_namer(key));
+
+    let (kind, constraints) = match (value.get("allOf"), value.get("oneOf").or(value.get("anyOf"))) {
+        (Some(_), _) => {
+            let nested = all_of_parser(&nested_key, value, at, ctx, unhandled)?;
+            (FieldKind::Object(Box::new(nested)), Constraints::default())
+        },
+        (None, Some(_)) => {
+            let nested = choice_parser(&nested_key, value, at, ctx, unhandled)?;
+            (FieldKind::Choice(Box::new(nested)), Constraints::default())
+        },
+        // Open component type to see what type of property it is
+        (None, None) => match at.get(value, "type").and_then(|v|at.join("type").as_str(v)).map_err(|e|vec![e])? {
+            "object" => {
+                let nested = component_parser(&nested_key, value, at, ctx, unhandled)?;
+                (FieldKind::Object(Box::new(nested)), Constraints::default())
+            },
+            t => value_parser(comp_key, key, t, value, at, ctx, unhandled).map_err(|e|vec![e])?,
+        },
+    };
+
+    Ok(Field {
+        key: key.to_owned(),
+        name: snaker(key),
+        required: required.contains(&key),
+        kind,
+        constraints,
+    })
+}
+
+// Kind and constraints of property that is not an object
+fn value_parser(comp_key: &str, key: &str, comp_type: &str, value: &Value, at: &Pointer, ctx: &Context,
+unhandled: &mut Vec<UnhandledKeyword>) -> Result<(FieldKind, Constraints), SchemaError> {
+    let type_keywords: &[&str] = match comp_type {
+        "string" => &STRING_KEYWORDS,
+        "number" | "integer" => &NUMBER_KEYWORDS,
+        "array" => &ARRAY_KEYWORDS,
+        _ => &[],
+    };
+
+    unhandled.extend(at.unhandled(value, &[PROPERTY_KEYWORDS.as_slice(), type_keywords].concat()));
+
+    let kind_and_constraints = match comp_type {
+        // Some properties have format key in which case dates and times are parsed into
+        // date and time types and FormattedValue enum is used for other formats
+        "string" => {
+            let format = at.str_at(value, "format")?;
+
+            let kind = match (format, format.and_then(Temporal::from_format)) {
+                (_, Some(t)) => FieldKind::Temporal(t),
+                (Some(f), None) => FieldKind::Formatted(enum_namer(f)),
+                (None, None) => FieldKind::Text,
+            };
 
-    for m in mods {
-        writeln!(mod_file, "pub use {}::*;", m).unwrap();
+            (kind, string_constraints_parser(value, at)?)
+        },
+        "number" => (FieldKind::Number, number_constraints_parser(value, at)?),
+        "integer" => (FieldKind::Integer, number_constraints_parser(value, at)?),
+        "boolean" => (FieldKind::Boolean, Constraints::default()),
+        "array" => (FieldKind::Array(array_parser(comp_key, key, value, at, ctx)?), Constraints::default()),
+        t => return Err(at.join("type").error(format!("unsupported property type `{}`", t))),
+    };
+
+    Ok(kind_and_constraints)
+}
+
+// Allowed values, pattern and length limits of string property
+fn string_constraints_parser(value: &Value, at: &Pointer) -> Result<Constraints, SchemaError> {
+    let allowed = match value.get("enum") {
+        Some(v) => {
+            let enum_at = at.join("enum");
+
+            let values = enum_at.as_array(v)?
+                .iter()
+                .enumerate()
+                .map(|(i, v)|enum_at.join(i.to_string()).as_str(v).map(String::from))
+                .collect::<Result<Vec<String>, SchemaError>>()?;
+
+            Some(values)
+        },
+        None => None,
+    };
+
+    // Pattern is compiled in generated code, make sure it's valid already here
+    let pattern = at.str_at(value, "pattern")?;
+
+    if let Some(p) = pattern {
+        regex::Regex::new(p).map_err(|e|at.join("pattern").error(format!("invalid pattern: {}", e)))?;
     }
 
-    formats
-}
\ No newline at end of file
+    let length = |k: &str| value.get(k).map(|v|at.join(k).as_u64(v)).transpose();
+
+    Ok(Constraints {
+        allowed,
+        pattern: pattern.map(String::from),
+        min_length: length("minLength")?,
+        max_length: length("maxLength")?,
+        ..Default::default()
+    })
+}
+
+// Numeric bounds of number or integer property
+fn number_constraints_parser(value: &Value, at: &Pointer) -> Result<Constraints, SchemaError> {
+    let bound = |k: &str| value.get(k).map(|v|at.join(k).as_f64(v)).transpose();
+
+    Ok(Constraints {
+        minimum: bound("minimum")?,
+        maximum: bound("maximum")?,
+        ..Default::default()
+    })
+}
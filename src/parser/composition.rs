@@ -0,0 +1,399 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::error::SchemaError;
+use crate::ir::{Choice, Component, Field, FieldKind, TypeRef, Variant, VariantKind};
+use crate::report::UnhandledKeyword;
+use crate::schema::{read_schema, schema_file_name};
+
+use super::component::{component_parser, field_parser, required_parser};
+use super::reference::type_ref_parser;
+use super::{struct_namer, Context, Pointer};
+
+
+// Compositions combine other schemas, either by reference or inline.
+// Every branch of "allOf" applies, branches are merged into single component with properties
+// of all of them. Property is required when any of the branches requires it.
+// Example of allOf restricting a component:
+// "RestrictedAmountType": {
+//     "allOf": [
+//       { "$ref": "BDNDR-CCTS_CCT_SchemaModule-1.1.json#/definitions/AmountType" },
+//       { "required": ["currencyID"] }
+//     ]
+//   },
+
+// Branches of "oneOf" and "anyOf" are alternatives, they become variants of untagged enum.
+// With "oneOf" exactly one branch has to match the value, "anyOf" accepts any number of them.
+// Example of oneOf:
+// "PartyReference": {
+//     "oneOf": [
+//       { "$ref": "UBL-CommonAggregateComponents-2.1.json#/definitions/Party" },
+//       { "type": "string" }
+//     ]
+//   },
+
+const ALL_OF_KEYWORDS: [&str; 7] = ["allOf", "type", "title", "description", "properties", "required", "additionalProperties"];
+const CHOICE_KEYWORDS: [&str; 5] = ["oneOf", "anyOf", "type", "title", "description"];
+const BRANCH_KEYWORDS: [&str; 3] = ["$ref", "title", "description"];
+const SCALAR_KEYWORDS: [&str; 3] = ["type", "title", "description"];
+
+// Schema merged into allOf component
+struct Branch {
+    value: Value,
+    file: PathBuf,
+    path: String,
+    /// Module of the schema file when it's not the one being parsed
+    module: Option<String>,
+    /// Branch is written in the definition itself and not behind a reference
+    own: bool,
+}
+
+impl Branch {
+    fn pointer(&self) -> Pointer<'_> {
+        Pointer { file: &self.file, path: self.path.to_owned() }
+    }
+}
+
+// Branches of allOf collected from the definition and the schemas it references
+struct Branches<'c> {
+    /// Schema file being parsed, references to other files are qualified with their module
+    root_file: &'c Path,
+    ctx: &'c Context<'c>,
+    /// References being followed, to catch the ones leading back to themselves
+    stack: Vec<String>,
+    list: Vec<Branch>,
+}
+
+/// Component merged from the definition and all of its `allOf` branches
+pub fn all_of_parser(key: &str, value: &Value, at: &Pointer, ctx: &Context, unhandled: &mut Vec<UnhandledKeyword>)
+-> Result<Component, Vec<SchemaError>> {
+    let mut branches = Branches { root_file: at.file, ctx, stack: vec![], list: vec![] };
+    branches.collect(value, at, None, true, unhandled).map_err(|e|vec![e])?;
+
+    let branches = branches.list;
+
+    let mut errors = vec![];
+    let mut required = vec![];
+
+    for b in branches.iter() {
+        if let Some(r) = b.value.get("required") {
+            match required_parser(r, &b.pointer().join("required")) {
+                Ok(r) => required.extend(r),
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+
+    let mut title = None;
+    let mut description = None;
+    let mut additional_props = vec![];
+    let mut fields: Vec<Field> = vec![];
+
+    for b in branches.iter() {
+        let b_at = b.pointer();
+
+        // Title and description of the definition itself come first, then from the branches
+        match (b_at.str_at(&b.value, "title"), b_at.str_at(&b.value, "description")) {
+            (Ok(t), Ok(d)) => {
+                title = title.or(t.map(String::from));
+                description = description.or(d.map(String::from));
+            },
+            (Err(e), _) | (_, Err(e)) => errors.push(e),
+        }
+
+        if let Some(v) = b.value.get("additionalProperties") {
+            match b_at.join("additionalProperties").as_bool(v) {
+                Ok(a) => additional_props.push(a),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        let props_at = b_at.join("properties");
+
+        let properties = match b.value.get("properties").map(|v|props_at.as_object(v)) {
+            Some(Ok(p)) => p,
+            Some(Err(e)) => {
+                errors.push(e);
+                continue;
+            },
+            None => continue,
+        };
+
+        // Referenced definitions report their keywords when their own schema is parsed
+        let mut ignored = vec![];
+
+        let branch_unhandled = match b.own {
+            true => &mut *unhandled,
+            false => &mut ignored,
+        };
+
+        for (pk, pv) in properties {
+            let mut field = match field_parser(key, pk, pv, &props_at.join(pk), &required, ctx, branch_unhandled) {
+                Ok(f) => f,
+                Err(e) => {
+                    errors.extend(e);
+                    continue;
+                },
+            };
+
+            if let Some(m) = &b.module {
+                module_qualifier(&mut field.kind, m);
+            }
+
+            // Later branch restricts the property further, it replaces the earlier one in place
+            match fields.iter_mut().find(|f|f.key.eq(&field.key)) {
+                Some(f) => *f = field,
+                None => fields.push(field),
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors)
+    }
+
+    Ok(Component {
+        key: key.to_owned(),
+        name: struct_namer(key),
+        title,
+        description,
+        // Additional properties are allowed only when every branch setting it allows them
+        additional_props: !additional_props.is_empty() && additional_props.iter().all(|a|*a),
+        fields,
+    })
+}
+
+impl Branches<'_> {
+    // Add schema and its allOf branches, references are followed into the schemas they point to
+    fn collect(&mut self, value: &Value, at: &Pointer, module: Option<String>, own: bool,
+    unhandled: &mut Vec<UnhandledKeyword>) -> Result<(), SchemaError> {
+        if own {
+            unhandled.extend(at.unhandled(value, &ALL_OF_KEYWORDS));
+        }
+
+        self.list.push(Branch {
+            value: value.clone(),
+            file: at.file.to_path_buf(),
+            path: at.path.to_owned(),
+            module: module.clone(),
+            own,
+        });
+
+        let all_of = match value.get("allOf") {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        let all_of_at = at.join("allOf");
+
+        for (i, b) in all_of_at.as_array(all_of)?.iter().enumerate() {
+            let b_at = all_of_at.join(i.to_string());
+
+            let ref_path = match b.get("$ref") {
+                Some(r) => b_at.join("$ref").as_str(r)?,
+                None => {
+                    self.collect(b, &b_at, module.clone(), own, unhandled)?;
+                    continue;
+                },
+            };
+
+            if own {
+                unhandled.extend(b_at.unhandled(b, &BRANCH_KEYWORDS));
+            }
+
+            let ref_at = b_at.join("$ref");
+            let (definition, file, path) = definition_resolver(ref_path, &ref_at, self.ctx)?;
+            let id = format!("{}{}", schema_file_name(&file), path);
+
+            if self.stack.contains(&id) {
+                return Err(ref_at.error(format!("`allOf` reference `{}` leads back to itself", ref_path)))
+            }
+
+            let def_module = match file.eq(self.root_file) {
+                true => None,
+                false => Some(self.ctx.naming.module_name(schema_file_name(&file))),
+            };
+
+            self.stack.push(id);
+            self.collect(&definition, &Pointer { file: &file, path }, def_module, false, unhandled)?;
+            self.stack.pop();
+        }
+
+        Ok(())
+    }
+}
+
+/// Enum with a variant for each `oneOf` or `anyOf` branch
+pub fn choice_parser(key: &str, value: &Value, at: &Pointer, ctx: &Context, unhandled: &mut Vec<UnhandledKeyword>)
+-> Result<Choice, Vec<SchemaError>> {
+    unhandled.extend(at.unhandled(value, &CHOICE_KEYWORDS));
+
+    let (keyword, exclusive) = match (value.get("oneOf").is_some(), value.get("anyOf").is_some()) {
+        (true, true) => return Err(vec![at.error("`oneOf` and `anyOf` in the same schema are not supported")]),
+        (true, false) => ("oneOf", true),
+        (false, _) => ("anyOf", false),
+    };
+
+    let branches_at = at.join(keyword);
+    let branches = at.get(value, keyword)
+        .and_then(|v|branches_at.as_array(v))
+        .map_err(|e|vec![e])?;
+
+    let mut errors = vec![];
+    let mut variants: Vec<Variant> = vec![];
+
+    let (title, description) = match (at.str_at(value, "title"), at.str_at(value, "description")) {
+        (Ok(t), Ok(d)) => (t.map(String::from), d.map(String::from)),
+        (t, d) => {
+            errors.extend(t.err().into_iter().chain(d.err()));
+            (None, None)
+        },
+    };
+
+    for (i, b) in branches.iter().enumerate() {
+        let mut name = variant_namer(b, i);
+
+        // Branches of the same type get their position in the name
+        if variants.iter().any(|v|v.name.eq(&name)) {
+            name = format!("{}{}", name, i + 1);
+        }
+
+        match variant_kind_parser(key, &name, b, &branches_at.join(i.to_string()), ctx, unhandled) {
+            Ok(kind) => variants.push(Variant { name, kind }),
+            Err(e) => errors.extend(e),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors)
+    }
+
+    Ok(Choice {
+        key: key.to_owned(),
+        name: struct_namer(key),
+        title,
+        description,
+        exclusive,
+        variants,
+    })
+}
+
+// Variant is named after the referenced struct or type of the branch
+fn variant_namer(value: &Value, i: usize) -> String {
+    if let Some(r) = value.get("$ref").and_then(|v|v.as_str()) {
+        return struct_namer(r.rsplit('/').next().unwrap_or(r))
+    }
+
+    let name = match value.get("type").and_then(|v|v.as_str()) {
+        Some("string") => "Text",
+        Some("number") => "Number",
+        Some("integer") => "Integer",
+        Some("boolean") => "Boolean",
+        _ => return format!("Variant{}", i + 1),
+    };
+
+    name.to_owned()
+}
+
+fn variant_kind_parser(enum_key: &str, name: &str, value: &Value, at: &Pointer, ctx: &Context,
+unhandled: &mut Vec<UnhandledKeyword>) -> Result<VariantKind, Vec<SchemaError>> {
+    // Inline objects are nested components named after the enum and variant, e.g. `PartyReferenceVariant2`
+    let nested_key = format!("{}{}", enum_key, name);
+
+    if let Some(r) = value.get("$ref") {
+        unhandled.extend(at.unhandled(value, &BRANCH_KEYWORDS));
+
+        let ref_at = at.join("$ref");
+        let ref_path = ref_at.as_str(r).map_err(|e|vec![e])?;
+        let target = type_ref_parser(ref_path, &ref_at, ctx).map_err(|e|vec![e])?;
+        let (definition, _, _) = definition_resolver(ref_path, &ref_at, ctx).map_err(|e|vec![e])?;
+
+        return Ok(VariantKind::Ref { target, validated: is_validated(&definition) })
+    }
+
+    if value.get("allOf").is_some() {
+        return all_of_parser(&nested_key, value, at, ctx, unhandled).map(|c|VariantKind::Object(Box::new(c)))
+    }
+
+    let branch_type = at.get(value, "type")
+        .and_then(|v|at.join("type").as_str(v))
+        .map_err(|e|vec![e])?;
+
+    if branch_type.eq("object") {
+        return component_parser(&nested_key, value, at, ctx, unhandled).map(|c|VariantKind::Object(Box::new(c)))
+    }
+
+    unhandled.extend(at.unhandled(value, &SCALAR_KEYWORDS));
+
+    match branch_type {
+        "string" => Ok(VariantKind::Text),
+        "number" => Ok(VariantKind::Number),
+        "integer" => Ok(VariantKind::Integer),
+        "boolean" => Ok(VariantKind::Boolean),
+        t => Err(vec![at.join("type").error(format!("unsupported branch type `{}`", t))]),
+    }
+}
+
+// Definitions generated with `validate` method: components, compositions and choices
+fn is_validated(definition: &Value) -> bool {
+    let is_object = definition.get("type").and_then(|v|v.as_str()).eq(&Some("object"));
+
+    (is_object && definition.get("properties").is_some())
+        || ["allOf", "oneOf", "anyOf"].iter().any(|k|definition.get(k).is_some())
+}
+
+// Definition `$ref` points to with its file and pointer. References without file part point
+// to the file of the reference.
+fn definition_resolver(ref_path: &str, at: &Pointer, ctx: &Context) -> Result<(Value, PathBuf, String), SchemaError> {
+    let (file_name, key) = ref_path.split_once("#/definitions/")
+        .ok_or_else(||at.error(format!("reference `{}` is not to definitions of schema file", ref_path)))?;
+
+    let file = match file_name.is_empty() {
+        true => at.file.to_path_buf(),
+        false => ctx.schemas.iter()
+            .find(|f|schema_file_name(f).eq(&schema_file_name(file_name)))
+            .cloned()
+            .ok_or_else(||at.error(format!("referenced schema `{}` is not among the schema files", file_name)))?,
+    };
+
+    let schema = read_schema(&file).map_err(|e|at.error(e.to_string()))?;
+
+    let definition = schema.get("definitions")
+        .and_then(|d|d.get(key))
+        .ok_or_else(||at.error(format!("definition `{}` not found from {:?}", key, file)))?
+        .clone();
+
+    let path = Pointer::new(&file).join("definitions").join(key).path;
+
+    Ok((definition, file, path))
+}
+
+// Point references of field merged from another schema file to the module of that file
+fn module_qualifier(kind: &mut FieldKind, module: &str) {
+    let qualify = |t: &mut TypeRef| if t.module.is_none() {
+        t.module = Some(module.to_owned());
+    };
+
+    match kind {
+        FieldKind::Array(a) => qualify(&mut a.item),
+        FieldKind::Object(c) => {
+            for f in c.fields.iter_mut() {
+                module_qualifier(&mut f.kind, module);
+            }
+        },
+        FieldKind::Choice(c) => {
+            for v in c.variants.iter_mut() {
+                match &mut v.kind {
+                    VariantKind::Ref { target, .. } => qualify(target),
+                    VariantKind::Object(c) => {
+                        for f in c.fields.iter_mut() {
+                            module_qualifier(&mut f.kind, module);
+                        }
+                    },
+                    _ => (),
+                }
+            }
+        },
+        _ => (),
+    }
+}
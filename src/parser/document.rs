@@ -0,0 +1,84 @@
+use serde_json::{Map, Value};
+
+// Maindoc schemas (UBL-Invoice-2.1.json, UBL-Order-2.1.json, ...) describe the document itself
+// in root level properties, next to definitions of the document type.
+// Root level properties hold the document as an array with single item referencing local definition.
+// Example of maindoc schema root:
+// {
+//     "required": [
+//       "Invoice"
+//     ],
+//     "properties": {
+//       "_D": {
+//         "type": "string"
+//       },
+//       "Invoice": {
+//         "items": {
+//           "$ref": "#/definitions/Invoice"
+//         },
+//         "maxItems": 1,
+//         "minItems": 1,
+//         "type": "array"
+//       }
+//     },
+//     "additionalProperties": false,
+//     "type": "object",
+//     "definitions": {
+//       "Invoice": { ... }
+//     }
+// }
+
+// Namespace declarations (_D, _A, _B, ...) are handled by generated Envelope type
+// instead of the document root struct.
+
+/// Document name and component definition for the document root of a maindoc schema,
+/// `None` for common library schemas
+pub fn document_definition(schema: &Value) -> Option<(String, Value)> {
+    let obj = schema.as_object()?;
+    let properties = obj.get("properties")?.as_object()?;
+
+    // Document is the property that references local definition
+    let doc_name = properties.iter().find_map(|(k, v)|{
+        v.get("items")
+            .and_then(|i|i.get("$ref"))
+            .and_then(|r|r.as_str())
+            .and_then(|r|r.strip_prefix("#/definitions/"))
+            .map(|_|k.to_owned())
+    })?;
+
+    // Root level object is a component like any other, without the definitions
+    let mut root = Map::new();
+
+    for (k, v) in obj {
+        if ["required", "additionalProperties"].contains(&k.as_str()) {
+            root.insert(k.to_owned(), v.to_owned());
+        }
+    }
+
+    let doc_properties = properties.iter()
+        .filter(|(k, _)|!is_namespace_key(k))
+        .map(|(k, v)|(k.to_owned(), v.to_owned()))
+        .collect::<Map<String, Value>>();
+
+    root.insert(String::from("properties"), Value::Object(doc_properties));
+
+    root.insert(String::from("title"), Value::from(format!("{}. Document", doc_name)));
+    root.insert(String::from("description"), Value::from(format!("Root of {} document.", doc_name)));
+    root.insert(String::from("type"), Value::from("object"));
+
+    Some((doc_name, Value::Object(root)))
+}
+
+/// Name of the struct generated for document root
+pub fn document_root_namer<T>(doc_name: T) -> String where T: AsRef<str> {
+    format!("{}Document", doc_name.as_ref())
+}
+
+// Namespace declaration keys are underscore followed by single uppercase letter
+fn is_namespace_key(key: &str) -> bool {
+    let mut chars = key.chars();
+
+    chars.next().eq(&Some('_'))
+        && chars.next().is_some_and(|c|c.is_ascii_uppercase())
+        && chars.next().is_none()
+}
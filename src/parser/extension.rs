@@ -1,13 +1,12 @@
-use std::fs::File;
-use std::io::Write;
+use crate::ir::Extension;
 
 use super::struct_namer;
 
 
-pub fn extension_writer(out_file: &mut File, key: &str) {
-    let ext_struct_name = struct_namer(key);
+// Extension is an object definition without properties, e.g. "ExtensionContentType".
 
-    writeln!(out_file, "\n\n#[derive(Debug, Clone, Deserialize, Serialize)]").unwrap();
-    writeln!(out_file, "#[serde(rename_all = \"PascalCase\")]").unwrap();
-    writeln!(out_file, "pub struct {} {{}}", ext_struct_name).unwrap();
-}
\ No newline at end of file
+pub const EXTENSION_KEYWORDS: [&str; 3] = ["type", "title", "description"];
+
+pub fn extension_parser(key: &str) -> Extension {
+    Extension { name: struct_namer(key) }
+}
@@ -1,91 +1,249 @@
 mod array;
+mod code_list;
 mod component;
+mod composition;
+mod document;
 mod reference;
 mod extension;
 
-use std::fs::{File, create_dir_all};
-use std::path::PathBuf;
-use std::io::Write;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
 
+use crate::error::{BuilderError, SchemaError};
+use crate::ir::{Document, Module};
+use crate::report::UnhandledKeyword;
+use crate::schema::{read_schema, schema_file_name};
 
-pub fn component_structifier<P>(in_file: PathBuf, out_path: P) -> (String, Vec<String>, Vec<(PathBuf, (PathBuf, String))>)
-where PathBuf: From<P> {
-    let contents = std::fs::read_to_string(&in_file)
-        .expect("Failed to read file");
+pub use array::single_item_linker;
+pub use code_list::{code_list_files, code_list_linker, code_list_parser};
 
-    let value: serde_json::Value = serde_json::from_str(&contents)
-        .expect("Failed to parse JSON");
 
-    let definitions = value.get("definitions")
-        .expect("No definitions");
+// Keywords handled at schema root, maindoc schemas have the document in root level properties
+const SCHEMA_KEYWORDS: [&str; 9] = [
+    "$schema", "$id", "title", "description", "definitions",
+    "properties", "required", "additionalProperties", "type",
+];
 
-    let out_subdir = in_file.file_name()
-        .expect("No file name")
-        .to_str()
-        .expect("File name is not a string")
-        .split_once(".json")
-        .expect("File name is not in the correct format")
-        .0;
 
-    let out_root = PathBuf::from(out_path);
-    let mut out_dir = out_root.clone();
-    let schema_name = filenamer(out_subdir);
-    out_dir.push(&schema_name);
-
-    create_dir_all(&out_dir).expect("Failed to create out directory");
-
-    // Run parsers and hope they generate the correct files
-    let formats = component::component_parser(definitions, &out_dir, &schema_name);
-    let post_processables = reference::reference_parser(definitions, &out_root, &out_dir);
-    
-    // Create mod.rs file in core_components directory
-    let mut mod_file = out_dir.clone();
-    mod_file.push("mod.rs");
-    
-    let mut mod_file = std::fs::File::create(&mod_file)
-    .expect("Failed to create file");
+/// How schema files are named as modules in the generated library
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModuleNaming {
+    /// Lowercased file name, e.g. `ubl_commonbasiccomponents_2_1`
+    #[default]
+    FileName,
+    /// File name without `UBL-`/`BDNDR-` prefix and version suffix, e.g. `common_basic_components`
+    Short,
+}
 
-    // Check if components dir is present
-    let mut components_dir = out_dir.clone();
-    components_dir.push("components");
+impl ModuleNaming {
+    pub fn module_name<T>(&self, schema: T) -> String where T: AsRef<str> {
+        match self {
+            Self::FileName => filenamer(schema),
+            Self::Short => {
+                let mut name = schema.as_ref().trim_end_matches(".json");
+
+                // Drop uppercase prefix like UBL- and BDNDR-
+                if let Some((prefix, rest)) = name.split_once('-') {
+                    if prefix.chars().all(|c|c.is_ascii_uppercase()) {
+                        name = rest;
+                    }
+                }
+
+                // Drop version suffix like -2.1
+                if let Some((rest, version)) = name.rsplit_once('-') {
+                    if version.chars().all(|c|c.is_ascii_digit() || c.eq(&'.')) {
+                        name = rest;
+                    }
+                }
+
+                snaker(name)
+            },
+        }
+    }
+}
 
-    // Same for the extensions file
-    let mut exts_file = out_dir.clone();
-    exts_file.push("extensions.rs");
+/// Settings shared by the parsers
+pub struct Context<'a> {
+    /// Rust path of the module generated modules are declared in
+    pub root_mod: &'a syn::Path,
+    pub naming: ModuleNaming,
+    /// All schema files, `$ref` targets of compositions are read from them
+    pub schemas: &'a [PathBuf],
+    /// Generate typestate builders for components
+    pub typestate_builders: bool,
+    /// Generate newtypes instead of type aliases for plain references
+    pub reference_newtypes: bool,
+    /// Generate `maxItems: 1` arrays as their item
+    pub collapse_single_items: bool,
+}
 
-    // Same for the references file
-    let mut refs_file = out_dir.clone();
-    refs_file.push("references.rs");
+/// JSON pointer to a value in schema file, errors found from the value are reported with it
+#[derive(Debug, Clone)]
+pub struct Pointer<'a> {
+    file: &'a Path,
+    path: String,
+}
 
-    if components_dir.exists() {
-        writeln!(mod_file, "mod components;").unwrap();
+impl<'a> Pointer<'a> {
+    /// Pointer to the root of schema file
+    pub fn new(file: &'a Path) -> Self {
+        Self { file, path: String::from("#") }
     }
-
-    if exts_file.exists() {
-        writeln!(mod_file, "mod extensions;").unwrap();
+    /// Pointer to a child value, key is escaped as in RFC 6901
+    pub fn join<T>(&self, key: T) -> Self where T: AsRef<str> {
+        let key = key.as_ref().replace('~', "~0").replace('/', "~1");
+        Self { file: self.file, path: format!("{}/{}", self.path, key) }
     }
-
-    if refs_file.exists() {
-        writeln!(mod_file, "mod references;").unwrap();
+    pub fn error<T>(&self, reason: T) -> SchemaError where T: Into<String> {
+        SchemaError {
+            file: self.file.to_path_buf(),
+            pointer: self.path.to_owned(),
+            reason: reason.into(),
+        }
     }
-
-    if exts_file.exists() || refs_file.exists() || components_dir.exists() {
-        writeln!(mod_file, "\n").unwrap();
+    /// Required key of the object pointed to
+    pub fn get<'v>(&self, value: &'v Value, key: &str) -> Result<&'v Value, SchemaError> {
+        value.get(key).ok_or_else(||self.error(format!("missing `{}`", key)))
+    }
+    pub fn as_object<'v>(&self, value: &'v Value) -> Result<&'v Map<String, Value>, SchemaError> {
+        value.as_object().ok_or_else(||self.error(format!("expected object, found {}", value_kind(value))))
+    }
+    pub fn as_array<'v>(&self, value: &'v Value) -> Result<&'v Vec<Value>, SchemaError> {
+        value.as_array().ok_or_else(||self.error(format!("expected array, found {}", value_kind(value))))
+    }
+    pub fn as_str<'v>(&self, value: &'v Value) -> Result<&'v str, SchemaError> {
+        value.as_str().ok_or_else(||self.error(format!("expected string, found {}", value_kind(value))))
+    }
+    pub fn as_u64(&self, value: &Value) -> Result<u64, SchemaError> {
+        value.as_u64().ok_or_else(||self.error(format!("expected non-negative integer, found {}", value_kind(value))))
     }
+    pub fn as_f64(&self, value: &Value) -> Result<f64, SchemaError> {
+        value.as_f64().ok_or_else(||self.error(format!("expected number, found {}", value_kind(value))))
+    }
+    pub fn as_bool(&self, value: &Value) -> Result<bool, SchemaError> {
+        value.as_bool().ok_or_else(||self.error(format!("expected boolean, found {}", value_kind(value))))
+    }
+    /// Keywords of the object pointed to that are not among the handled ones
+    pub fn unhandled(&self, value: &Value, handled: &[&str]) -> Vec<UnhandledKeyword> {
+        value.as_object()
+            .map(|o|o.keys()
+                .filter(|k|!handled.contains(&k.as_str()))
+                .map(|k|UnhandledKeyword {
+                    file: self.file.to_path_buf(),
+                    pointer: self.join(k).path,
+                    keyword: k.to_owned(),
+                })
+                .collect())
+            .unwrap_or_default()
+    }
+    /// Optional string value of the object pointed to
+    pub fn str_at<'v>(&self, value: &'v Value, key: &str) -> Result<Option<&'v str>, SchemaError> {
+        value.get(key).map(|v|self.join(key).as_str(v)).transpose()
+    }
+}
 
-    if exts_file.exists() {
-        writeln!(mod_file, "pub use extensions::*;").unwrap();
+// Name of the JSON type for error messages
+fn value_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
     }
+}
 
-    if refs_file.exists() {
-        writeln!(mod_file, "pub use references::*;").unwrap();
+/// Read schema file into a module. Errors in the definitions are collected to `errors`
+/// so that all of them can be reported at once, same for keywords that are not handled.
+pub fn module_parser(in_file: &Path, ctx: &Context, errors: &mut Vec<SchemaError>, unhandled: &mut Vec<UnhandledKeyword>)
+-> Result<Module, BuilderError> {
+    let value = read_schema(in_file)?;
+    let root = Pointer::new(in_file);
+
+    unhandled.extend(root.unhandled(&value, &SCHEMA_KEYWORDS));
+
+    let mut module = Module {
+        name: ctx.naming.module_name(schema_file_name(in_file)),
+        components: vec![],
+        extensions: vec![],
+        references: vec![],
+        choices: vec![],
+        document: None,
+    };
+
+    let definitions = match root.get(&value, "definitions").and_then(|v|root.join("definitions").as_object(v)) {
+        Ok(d) => d,
+        Err(e) => {
+            errors.push(e);
+            return Ok(module)
+        },
+    };
+
+    for (k, v) in definitions {
+        let at = root.join("definitions").join(k);
+
+        let obj = match at.as_object(v) {
+            Ok(o) => o,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            },
+        };
+
+        let is_object = obj.get("type").and_then(|v|v.as_str()).eq(&Some("object"));
+
+        // Compositions are recognized by their keyword regardless of type
+        if obj.contains_key("allOf") {
+            match composition::all_of_parser(k, v, &at, ctx, unhandled) {
+                Ok(c) => module.components.push(c),
+                Err(e) => errors.extend(e),
+            }
+
+            continue;
+        }
+
+        if obj.contains_key("oneOf") || obj.contains_key("anyOf") {
+            match composition::choice_parser(k, v, &at, ctx, unhandled) {
+                Ok(c) => module.choices.push(c),
+                Err(e) => errors.extend(e),
+            }
+
+            continue;
+        }
+
+        match (is_object, obj.contains_key("properties"), obj.contains_key("$ref")) {
+            (true, true, _) => match component::component_parser(k, v, &at, ctx, unhandled) {
+                Ok(c) => module.components.push(c),
+                Err(e) => errors.extend(e),
+            },
+            // Objects without properties are extensions
+            (true, false, _) => {
+                unhandled.extend(at.unhandled(v, &extension::EXTENSION_KEYWORDS));
+                module.extensions.push(extension::extension_parser(k));
+            },
+            (false, _, true) => match reference::reference_parser(k, v, &at, ctx, unhandled) {
+                Ok(r) => module.references.push(r),
+                Err(e) => errors.push(e),
+            },
+            // Other definitions are not supported and no code is generated for them
+            _ => unhandled.extend(at.unhandled(v, &[])),
+        }
     }
 
-    if components_dir.exists() {
-        writeln!(mod_file, "pub use components::*;").unwrap();
+    // Maindoc schemas have the document root as an additional component
+    if let Some((doc_name, doc_root)) = document::document_definition(&value) {
+        let root_name = document::document_root_namer(&doc_name);
+
+        match component::component_parser(&root_name, &doc_root, &root, ctx, unhandled) {
+            Ok(c) => module.components.push(c),
+            Err(e) => errors.extend(e),
+        }
+
+        module.document = Some(Document { name: doc_name, root: root_name });
     }
 
-    (schema_name, formats, post_processables)
+    Ok(module)
 }
 
 pub fn struct_namer<T>(key: T) -> String where T: AsRef<str> {
@@ -148,6 +306,8 @@ pub fn snaker<T>(key: T) -> String where T: AsRef<str> {
             continue;
         }
 
+        prev_underscore = false;
+
         // Lowecase char, take it and proceed
         if s.is_lowercase() {
             mod_name.push(s);
@@ -192,20 +352,3 @@ pub fn filenamer<T>(name: T) -> String where T: AsRef<str> {
 
     file.replace("__", "_")
 }
-
-pub fn impl_title_and_descr_writer(title: Option<&str>, description: Option<&str>, out_file: &mut File) {
-    // make impl for the struct with methods returning static title and description
-    if let Some(s) = title {
-        // writeln!(out_file, "    #[allow(dead_code)]").unwrap();
-        writeln!(out_file, "    pub fn title() -> &'static str {{").unwrap();
-        writeln!(out_file, "        \"{}\"", s).unwrap();
-        writeln!(out_file, "    }}").unwrap();
-    }
-
-    if let Some(s) = description {
-        // writeln!(out_file, "    #[allow(dead_code)]").unwrap();
-        writeln!(out_file, "    pub fn description() -> &'static str {{").unwrap();
-        writeln!(out_file, "        \"{}\"", s).unwrap();
-        writeln!(out_file, "    }}").unwrap();
-    }
-}
@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::PathBuf;
+
+
+/// Summary of generation returned by [`Builder::generate`](crate::Builder::generate)
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// Schema keywords no code was generated for
+    pub unhandled: Vec<UnhandledKeyword>,
+}
+
+/// Keyword in schema file the generator does not handle
+#[derive(Debug, Clone)]
+pub struct UnhandledKeyword {
+    pub file: PathBuf,
+    /// JSON pointer to the keyword, e.g. `#/definitions/Address/properties/ID/exclusiveMinimum`
+    pub pointer: String,
+    pub keyword: String,
+}
+
+impl Report {
+    pub fn is_empty(&self) -> bool {
+        self.unhandled.is_empty()
+    }
+}
+
+// Unhandled keywords grouped by keyword, locations listed under each
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut keywords: BTreeMap<&str, Vec<&UnhandledKeyword>> = BTreeMap::new();
+
+        for u in self.unhandled.iter() {
+            keywords.entry(u.keyword.as_str()).or_default().push(u);
+        }
+
+        for (keyword, found) in keywords {
+            writeln!(f, "unhandled keyword `{}` in {} places:", keyword, found.len())?;
+
+            for u in found {
+                writeln!(f, "  {:?} at {}", u.file, u.pointer)?;
+            }
+        }
+
+        Ok(())
+    }
+}
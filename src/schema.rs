@@ -0,0 +1,188 @@
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::error::BuilderError;
+
+// Schemas reference definitions of other schema files by file name:
+// "$ref": "UBL-CommonBasicComponents-2.1.json#/definitions/ID"
+// References within the same file have no file part:
+// "$ref": "#/definitions/UBLExtension"
+// Referenced files have to be processed before the files referencing them.
+
+
+/// JSON files in schema directory, sorted by file name
+pub fn schema_dir_files(dir: &Path) -> Result<Vec<PathBuf>, BuilderError> {
+    let read_err = |err| BuilderError::Read { file: dir.to_path_buf(), err };
+    let mut files = vec![];
+
+    for entry in std::fs::read_dir(dir).map_err(read_err)? {
+        let path = entry.map_err(read_err)?.path();
+
+        if path.is_file() && path.extension().is_some_and(|e|e.eq("json")) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Order schema files so that referenced files come before the files referencing them
+pub fn dependency_order(files: Vec<PathBuf>) -> Result<Vec<PathBuf>, BuilderError> {
+    let mut nodes = BTreeMap::new();
+
+    for file in files {
+        let name = schema_file_name(&file);
+
+        if nodes.contains_key(&name) {
+            return Err(BuilderError::DuplicateSchema(name))
+        }
+
+        let refs = referenced_files(&read_schema(&file)?);
+        nodes.insert(name, (file, refs));
+    }
+
+    // Every referenced file has to be among the schema sources
+    for (name, (file, refs)) in nodes.iter() {
+        if let Some(r) = refs.iter().find(|r|!nodes.contains_key(*r) && r.ne(&name)) {
+            return Err(BuilderError::MissingSchema { file: file.to_owned(), reference: r.to_owned() })
+        }
+    }
+
+    // Take files whose references are all processed, in file name order to keep output stable
+    let mut ordered = vec![];
+    let mut done = BTreeSet::new();
+
+    while done.len() < nodes.len() {
+        let next = nodes.iter()
+            .find(|(name, (_, refs))|!done.contains(*name) && refs.iter().all(|r|done.contains(r) || r.eq(*name)))
+            .map(|(name, (file, _))|(name.to_owned(), file.to_owned()));
+
+        match next {
+            Some((name, file)) => {
+                done.insert(name);
+                ordered.push(file);
+            },
+            None => return Err(BuilderError::Cycle(find_cycle(&nodes, &done))),
+        }
+    }
+
+    Ok(ordered)
+}
+
+/// Read and parse schema file
+pub fn read_schema(file: &Path) -> Result<Value, BuilderError> {
+    let contents = std::fs::read_to_string(file)
+        .map_err(|err|BuilderError::Read { file: file.to_path_buf(), err })?;
+
+    serde_json::from_str(&contents)
+        .map_err(|err|BuilderError::Parse { file: file.to_path_buf(), err })
+}
+
+/// File names of schemas referenced anywhere in the given schema
+pub fn referenced_files(value: &Value) -> BTreeSet<String> {
+    let mut refs = BTreeSet::new();
+
+    match value {
+        Value::Object(o) => {
+            for (k, v) in o {
+                match (k.as_str(), v.as_str()) {
+                    ("$ref", Some(s)) => {
+                        if let Some(f) = s.split_once('#').map(|t|t.0).filter(|f|!f.is_empty()) {
+                            refs.insert(schema_file_name(f));
+                        }
+                    },
+                    _ => refs.extend(referenced_files(v)),
+                }
+            }
+        },
+        Value::Array(a) => {
+            for v in a {
+                refs.extend(referenced_files(v));
+            }
+        },
+        _ => (),
+    }
+
+    refs
+}
+
+/// File name without directories, schemas are referenced and matched by it
+pub fn schema_file_name<P>(file: P) -> String where P: AsRef<Path> {
+    let file = file.as_ref();
+
+    file.file_name()
+        .map(|s|s.to_string_lossy().to_string())
+        .unwrap_or(file.to_string_lossy().to_string())
+}
+
+// Follow references of unprocessed files until one repeats
+fn find_cycle(nodes: &BTreeMap<String, (PathBuf, BTreeSet<String>)>, done: &BTreeSet<String>) -> Vec<String> {
+    let mut path: Vec<String> = vec![];
+    let mut current = nodes.keys().find(|n|!done.contains(*n)).cloned();
+
+    while let Some(name) = current {
+        if let Some(i) = path.iter().position(|p|p.eq(&name)) {
+            let mut cycle = path.split_off(i);
+            cycle.push(name);
+            return cycle
+        }
+
+        current = nodes.get(&name)
+            .and_then(|(_, refs)|refs.iter().find(|r|!done.contains(*r) && r.ne(&&name)).cloned());
+
+        path.push(name);
+    }
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Schema file referencing definitions of the given files
+    fn write_schema(dir: &Path, name: &str, refs: &[&str]) -> PathBuf {
+        let properties: serde_json::Map<String, Value> = refs.iter()
+            .map(|r|(r.to_string(), serde_json::json!({"$ref": format!("{}#/definitions/ID", r)})))
+            .collect();
+
+        let file = dir.join(name);
+        let schema = serde_json::json!({"definitions": {"ID": {"properties": properties, "type": "object"}}});
+        std::fs::write(&file, schema.to_string()).unwrap();
+        file
+    }
+
+    #[test]
+    fn referenced_files_come_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write_schema(dir.path(), "A.json", &["B.json", "A.json"]);
+        let b = write_schema(dir.path(), "B.json", &[]);
+
+        assert_eq!(dependency_order(vec![a.clone(), b.clone()]).unwrap(), [b, a]);
+    }
+
+    #[test]
+    fn two_file_cycle_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write_schema(dir.path(), "A.json", &["B.json"]);
+        let b = write_schema(dir.path(), "B.json", &["A.json"]);
+
+        assert!(matches!(dependency_order(vec![a, b]), Err(BuilderError::Cycle(c)) if c == ["A.json", "B.json", "A.json"]));
+    }
+
+    #[test]
+    fn missing_referenced_file_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write_schema(dir.path(), "A.json", &["B.json"]);
+
+        match dependency_order(vec![a.clone()]) {
+            Err(BuilderError::MissingSchema { file, reference }) => {
+                assert_eq!(file, a);
+                assert_eq!(reference, "B.json");
+            },
+            other => panic!("expected missing schema, got {:?}", other.map_err(|e|e.to_string())),
+        }
+    }
+}
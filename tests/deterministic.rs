@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use ubl_rs_builder::Builder;
+
+
+// Relative path and contents of every file under the directory
+fn read_tree(dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+    let mut files = BTreeMap::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(d) = dirs.pop() {
+        for entry in std::fs::read_dir(&d).unwrap() {
+            let path = entry.unwrap().path();
+
+            match path.is_dir() {
+                true => dirs.push(path),
+                false => {
+                    let contents = std::fs::read(&path).unwrap();
+                    files.insert(path.strip_prefix(dir).unwrap().to_path_buf(), contents);
+                },
+            }
+        }
+    }
+
+    files
+}
+
+#[test]
+fn generated_trees_are_identical() {
+    let builder = Builder::new()
+        .schema_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/schemas"))
+        .manifest(true)
+        .exporter(true);
+
+    let first = tempfile::tempdir().unwrap();
+    let second = tempfile::tempdir().unwrap();
+
+    builder.clone().out_dir(first.path()).generate().unwrap();
+    builder.out_dir(second.path()).generate().unwrap();
+
+    let first = read_tree(first.path());
+    let second = read_tree(second.path());
+
+    assert!(!first.is_empty());
+    assert_eq!(first.keys().collect::<Vec<_>>(), second.keys().collect::<Vec<_>>());
+
+    for (path, contents) in first.iter() {
+        assert!(contents.eq(&second[path]), "{:?} differs between runs", path);
+    }
+}
@@ -0,0 +1,49 @@
+// Test of the crate generated with collapsed single items, copied to its tests directory
+use collapsed::ubl_commonaggregatecomponents_2_1::{InvoiceLine, Item, Party};
+use collapsed::ubl_invoice_2_1::InvoiceEnvelope;
+use collapsed::Componentable;
+
+const INVOICE: &str = r#"{
+    "_D": "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2",
+    "_A": "urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2",
+    "_B": "urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2",
+    "Invoice": [{
+        "ID": [{"_": "123"}],
+        "IssueDate": [{"_": "2011-09-22"}],
+        "AccountingSupplierParty": [{
+            "Party": [{"PartyName": [{"Name": [{"_": "Custom Cotter Pins"}]}]}]
+        }],
+        "AccountingCustomerParty": [{
+            "Party": [{"PartyName": [{"Name": [{"_": "North American Veeblefetzer"}]}]}]
+        }],
+        "LegalMonetaryTotal": [{"PayableAmount": [{"_": 100.00, "currencyID": "CAD"}]}],
+        "InvoiceLine": [{
+            "ID": [{"_": "1"}],
+            "LineExtensionAmount": [{"_": 100.00, "currencyID": "CAD"}],
+            "Item": [{"Description": [{"_": "Cotter pin, MIL-SPEC"}]}]
+        }]
+    }]
+}"#;
+
+#[test]
+fn collapsed_structs_stay_small() {
+    for size in [size_of::<InvoiceLine>(), size_of::<Item>(), size_of::<Party>()] {
+        assert!(size < 4096, "struct is {} bytes", size);
+    }
+}
+
+#[test]
+fn sample_document_is_read_and_written() {
+    let envelope: InvoiceEnvelope = serde_json::from_str(INVOICE).unwrap();
+    let invoice = &envelope.document().invoice;
+
+    assert_eq!(invoice.id._uc, "123");
+    assert_eq!(invoice.invoice_line.items[0].item.description.as_ref().unwrap().items[0]._uc, "Cotter pin, MIL-SPEC");
+    assert!(envelope.validate_all().is_empty());
+    assert!(invoice.validate().is_ok());
+
+    let written = serde_json::to_value(&envelope).unwrap();
+    let read = serde_json::from_str::<serde_json::Value>(INVOICE).unwrap();
+
+    assert_eq!(written["Invoice"], read["Invoice"]);
+}
@@ -0,0 +1,247 @@
+// Test of the crate generated from features fixture schema, copied to its tests directory
+use features::features_1_0::{CurrencyCodeType, Label, Link, Mark, Package, Tag};
+use features::ubl_package_2_1::PackageEnvelope;
+use features::{Componentable, FormattedValue, UnknownField};
+use serde_json::{json, Value};
+
+fn package(changes: Value) -> Value {
+    let mut package = json!({
+        "Measure": [{"_": 12.5, "unitCode": "KGM"}],
+        "Count": [{"_": 3}],
+        "Label": [{"_": "AB"}, "fragile"],
+        "Tag": [7, {"name": "blue"}],
+        "Dimensions": {"width": 1.5, "height": 20}
+    });
+
+    for (k, v) in changes.as_object().unwrap() {
+        package[k] = v.clone();
+    }
+
+    package
+}
+
+fn read(value: Value) -> Result<Package, serde_json::Error> {
+    serde_json::from_value(value)
+}
+
+// Pointer and code of every violation
+fn violations(value: Value) -> Vec<(String, &'static str)> {
+    read(value).unwrap()
+        .validate_all()
+        .iter()
+        .map(|v|(v.pointer.to_owned(), v.code()))
+        .collect()
+}
+
+#[test]
+fn valid_package_is_read_and_written() {
+    let value = package(json!({}));
+    let package = read(value.clone()).unwrap();
+
+    assert!(package.validate().is_ok());
+    assert!(package.validate_all().is_empty());
+    assert!(matches!(package.label.as_ref().unwrap().items[..], [Label::CodeType(_), Label::Text(_)]));
+    assert!(matches!(package.tag.as_ref().unwrap().items[..], [Tag::Integer(7), Tag::Variant2(_)]));
+    assert_eq!(package.dimensions.as_ref().unwrap().height, Some(20));
+    assert_eq!(serde_json::to_value(&package).unwrap(), value);
+}
+
+#[test]
+fn all_of_merges_required_properties() {
+    assert!(read(package(json!({"Measure": [{"_": 1}]}))).is_err());
+}
+
+#[test]
+fn integers_are_whole_numbers() {
+    assert!(read(package(json!({"Count": [{"_": 1.5}]}))).is_err());
+    assert!(read(package(json!({"Dimensions": {"width": 1, "height": 2.5}}))).is_err());
+}
+
+#[test]
+fn inline_objects_reject_unknown_fields() {
+    assert!(read(package(json!({"Dimensions": {"width": 1, "depth": 2}}))).is_err());
+}
+
+#[test]
+fn lenient_read_removes_unknown_fields() {
+    let value = package(json!({"Dimensions": {"width": 1.5, "depth": 2}}));
+    let (package, removed): (Package, _) = features::from_value_lenient(value.clone()).unwrap();
+
+    assert_eq!(removed, [UnknownField { pointer: String::from("/Dimensions/depth"), value: json!(2) }]);
+    assert_eq!(serde_json::to_value(&package).unwrap()["Dimensions"], json!({"width": 1.5}));
+
+    // Strict read still fails
+    assert!(read(value).is_err());
+}
+
+#[test]
+fn lenient_read_picks_choice_variant_without_unknown_fields() {
+    let value = package(json!({"Mark": [{"size": 2}, {"color": "red", "size": 3}]}));
+    let (package, removed): (Package, _) = features::from_value_lenient(value.clone()).unwrap();
+    let marks = &package.mark.as_ref().unwrap().items;
+
+    assert!(matches!(marks[..], [Mark::Variant2(_), Mark::Variant1(_)]));
+    assert!(marks.iter().all(|m|m.validate().is_ok()));
+    assert_eq!(removed, [UnknownField { pointer: String::from("/Mark/1/size"), value: json!(3) }]);
+    assert!(read(value).is_err());
+}
+
+#[test]
+fn lenient_read_of_envelope_keeps_declarations() {
+    let value = json!({
+        "_D": "urn:oasis:names:specification:ubl:schema:xsd:Package-2",
+        "Package": [package(json!({"Count": [{"_": 3, "unit": "pcs"}]}))],
+    });
+    let (envelope, removed): (PackageEnvelope, _) = features::from_value_lenient(value).unwrap();
+
+    assert_eq!(removed, [UnknownField { pointer: String::from("/Package/0/Count/0/unit"), value: json!("pcs") }]);
+    assert!(envelope.validate_all().is_empty());
+}
+
+#[test]
+fn dates_keep_their_offset() {
+    for date in ["2024-01-02", "2024-01-02Z", "2024-01-02+02:00", "2024-01-02-05:30"] {
+        let value = package(json!({"Shipped": date}));
+        let package = read(value.clone()).unwrap();
+
+        assert_eq!(package.shipped.unwrap().value, chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(serde_json::to_value(&package).unwrap(), value);
+    }
+
+    assert!(read(package(json!({"Shipped": "2024-01-02+25:00"}))).is_err());
+}
+
+#[test]
+fn uris_are_read_validated_and_written() {
+    let value = package(json!({"Link": [
+        {"href": "https://example.com/package"},
+        {"href": "https://example.com/package", "mirror": "ftp://mirror.example.com/package"},
+    ]}));
+    let linked = read(value.clone()).unwrap();
+    let links = &linked.link.as_ref().unwrap().items;
+
+    assert!(matches!(&links[0], Link { href: FormattedValue::Uri(_), mirror: None }));
+    assert!(matches!(&links[1].mirror, Some(FormattedValue::Uri(u)) if u == "ftp://mirror.example.com/package"));
+    assert!(linked.validate_all().is_empty());
+    assert_eq!(serde_json::to_value(&linked).unwrap(), value);
+
+    assert_eq!(violations(package(json!({"Link": [{"href": "", "mirror": ""}]}))), [
+        (String::from("/Link/0/href"), "is_empty"),
+        (String::from("/Link/0/mirror"), "optional_empty"),
+    ]);
+}
+
+#[test]
+fn uri_fields_reject_values_of_other_formats() {
+    let mut link: Link = serde_json::from_value(json!({"href": "https://example.com/package"})).unwrap();
+    link.href = FormattedValue::new_date_from_str("2024-01-02").unwrap();
+    link.mirror = Some(FormattedValue::new_date_from_str("2024-01-02").unwrap());
+
+    assert_eq!(link.validate().unwrap_err().code(), "bad_format");
+    assert_eq!(link.validate_all().iter().map(|v|v.code()).collect::<Vec<_>>(), ["bad_format", "optional_bad_format"]);
+}
+
+#[test]
+fn malformed_dates_are_rejected() {
+    for date in ["2024-02-30", "02.01.2024", "2024-01-02T10:00:00", "tomorrow"] {
+        assert!(read(package(json!({"Shipped": date}))).is_err(), "{}", date);
+    }
+}
+
+#[test]
+fn numeric_bounds_are_checked() {
+    assert_eq!(violations(package(json!({"Measure": [{"_": 1000.5, "unitCode": "KGM"}]}))), [
+        (String::from("/Measure/0/_"), "above_maximum"),
+    ]);
+    assert_eq!(violations(package(json!({"Measure": [{"_": -1, "unitCode": "KGM"}], "Count": [{"_": 0}]}))), [
+        (String::from("/Count/0/_"), "below_minimum"),
+        (String::from("/Measure/0/_"), "below_minimum"),
+    ]);
+    assert_eq!(violations(package(json!({"Dimensions": {"width": 1, "height": 101}}))), [
+        (String::from("/Dimensions/height"), "above_maximum"),
+    ]);
+}
+
+#[test]
+fn enum_is_checked() {
+    let package = read(package(json!({"Measure": [{"_": 1, "unitCode": "LTR"}]}))).unwrap();
+
+    assert_eq!(package.validate().unwrap_err().code(), "component_validation");
+    assert_eq!(package.validate_all().iter().map(|v|v.code()).collect::<Vec<_>>(), ["not_allowed"]);
+}
+
+#[test]
+fn one_of_needs_exactly_one_valid_branch() {
+    // Code failing pattern or length is no valid code type, and objects aren't text
+    for code in ["ab", "A", "ABCD"] {
+        let package = read(package(json!({"Label": [{"_": code}]}))).unwrap();
+        let label = &package.label.as_ref().unwrap().items[0];
+
+        assert_eq!(label.validate().unwrap_err().code(), "no_match");
+    }
+}
+
+#[test]
+fn choice_reports_violations_of_the_branch_it_reads_as() {
+    assert_eq!(violations(package(json!({"Label": [{"_": "ab"}, {"_": "ABCD"}]}))), [
+        (String::from("/Label/0/_"), "pattern_mismatch"),
+        (String::from("/Label/1/_"), "too_long"),
+    ]);
+}
+
+#[test]
+fn any_of_needs_a_valid_branch() {
+    let tagged = read(package(json!({"Tag": [{"name": ""}]}))).unwrap();
+
+    assert_eq!(tagged.tag.as_ref().unwrap().items[0].validate().unwrap_err().code(), "no_match");
+    assert_eq!(tagged.validate_all().iter().map(|v|(v.pointer.as_str(), v.code())).collect::<Vec<_>>(), [
+        ("/Tag/0/name", "is_empty"),
+        ("/Tag/0/name", "too_short"),
+    ]);
+    assert!(read(package(json!({"Tag": ["blue"]}))).is_err());
+}
+
+#[test]
+fn code_list_type_is_validated_as_its_target_and_list() {
+    let code = |value: Value| serde_json::from_value::<CurrencyCodeType>(value).unwrap();
+    let error = |value: Value| code(value).validate().map(|_|()).map_err(|e|e.code());
+
+    assert_eq!(error(json!({"_": "EUR"})), Ok(()));
+    assert_eq!(error(json!({"_": "EURO"})), Err("not_in_code_list"));
+    assert_eq!(error(json!({"_": "EUR", "listID": ""})), Err("optional_empty"));
+    assert_eq!(error(json!({"_": ""})), Err("is_empty"));
+
+    let report = code(json!({"_": "EUR", "listID": ""})).validate_all();
+    assert_eq!(report.iter().map(|v|(v.pointer.as_str(), v.code())).collect::<Vec<_>>(), [("/listID", "optional_empty")]);
+}
+
+#[test]
+fn unlisted_codes_are_allowed_per_call() {
+    let code = serde_json::from_value::<CurrencyCodeType>(json!({"_": "EURO"})).unwrap();
+
+    assert!(features::validate_allowing_unlisted_codes(&code).is_ok());
+    assert!(features::validate_all_allowing_unlisted_codes(&code).is_empty());
+    assert_eq!(code.validate().unwrap_err().code(), "not_in_code_list");
+    assert_eq!(code.validate_all().len(), 1);
+}
+
+#[test]
+fn envelope_checks_namespace_declarations() {
+    let envelope = |declarations: Value| {
+        let mut value = json!({
+            "_D": "urn:oasis:names:specification:ubl:schema:xsd:Package-2",
+            "Package": [package(json!({}))],
+        });
+
+        for (k, v) in declarations.as_object().unwrap() {
+            value[k] = v.clone();
+        }
+
+        serde_json::from_value::<PackageEnvelope>(value).map_err(|e|e.to_string())
+    };
+
+    assert!(envelope(json!({"_B": "urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2"})).is_ok());
+    assert!(envelope(json!({"_D": "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2"})).unwrap_err().contains("`_D`"));
+    assert!(envelope(json!({"_B": "urn:example"})).unwrap_err().contains("`_B`"));
+    assert!(envelope(json!({"_X": "urn:example"})).unwrap_err().contains("`_X` is not known"));
+}
@@ -0,0 +1,28 @@
+// Test of the crate generated with reference newtypes, copied to its tests directory
+use newtypes::bdndr_ccts_cct_schemamodule_1_1::TextType;
+use newtypes::bdndr_unqualifieddatatypes_1_1::NameType;
+use newtypes::ubl_commonbasiccomponents_2_1::{ID, Name};
+use newtypes::Componentable;
+use serde_json::json;
+
+#[test]
+fn newtype_converts_from_and_into_its_target() {
+    let name = Name::from(NameType::from(TextType::new("Acme").get_validated().unwrap()));
+
+    let name_type: NameType = name.clone().into();
+    let text_type: TextType = name_type.into();
+    assert_eq!(text_type._uc, "Acme");
+
+    assert!(name.validate().is_ok());
+    assert_eq!(serde_json::to_value(&name).unwrap(), json!({"_": "Acme"}));
+    assert_eq!(serde_json::from_value::<Name>(json!({"_": "Acme"})).unwrap()._uc, "Acme");
+}
+
+#[test]
+fn newtype_derefs_to_its_target() {
+    let mut id = ID::from("123");
+    assert_eq!(id._uc, "123");
+
+    id._uc = String::from("456");
+    assert_eq!(serde_json::to_value(&id).unwrap(), json!({"_": "456"}));
+}
@@ -0,0 +1,10 @@
+// Identifier doesn't compile where name is expected
+use newtypes::ubl_commonbasiccomponents_2_1::{ID, Name};
+
+fn name_of(name: Name) -> Name {
+    name
+}
+
+fn main() {
+    let _ = name_of(ID::from("Acme"));
+}
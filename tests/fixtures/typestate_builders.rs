@@ -0,0 +1,31 @@
+// Test of the crate generated with typestate builders, copied to its tests directory
+use serde_json::json;
+use typestate::features_1_0::{CodeType, Version};
+use typestate::Componentable;
+
+#[test]
+fn component_is_built_field_by_field() {
+    let version = Version::builder()
+        .channel("beta")
+        .build_(7)
+        .label("Seven")
+        .major(1)
+        .build()
+        .get_validated()
+        .unwrap();
+
+    assert_eq!(serde_json::to_value(&version).unwrap(), json!({
+        "major": 1,
+        "build": 7,
+        "channel": "beta",
+        "label": "Seven",
+    }));
+}
+
+#[test]
+fn built_component_is_validated() {
+    let code = CodeType::builder().uc("ab").build();
+
+    assert!(code.as_validated().is_err());
+    assert!(CodeType::builder().uc("AB").build().get_validated().unwrap().validate().is_ok());
+}
@@ -0,0 +1,6 @@
+// Building without every required field doesn't compile, `channel` is missing
+use typestate::features_1_0::Version;
+
+fn main() {
+    let _ = Version::builder().major(1).build_(7).build();
+}
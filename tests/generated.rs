@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use ubl_rs_builder::Builder;
+
+
+// Generated crates are built and tested with cargo. They share one target directory so that
+// their dependencies are compiled only once.
+
+const TMP_DIR: &str = env!("CARGO_TARGET_TMPDIR");
+
+fn fixture(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+}
+
+// Cargo command run in generated crate
+fn cargo(crate_dir: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO"));
+    cmd.current_dir(crate_dir).env("CARGO_TARGET_DIR", Path::new(TMP_DIR).join("target"));
+    cmd
+}
+
+// Generate crate with the builder and run `cargo test` in it with given test file
+fn test_generated(name: &str, builder: Builder, test_file: &str) -> PathBuf {
+    let crate_dir = Path::new(TMP_DIR).join(name);
+
+    builder
+        .out_dir(&crate_dir)
+        .crate_name(name)
+        .manifest(true)
+        .generate()
+        .unwrap();
+
+    std::fs::create_dir_all(crate_dir.join("tests")).unwrap();
+    std::fs::copy(fixture(test_file), crate_dir.join("tests/generated.rs")).unwrap();
+
+    // Dependencies are resolved from local registry cache when possible, so that the tests
+    // can be run without network
+    if !crate_dir.join("Cargo.lock").exists() {
+        let offline = cargo(&crate_dir).args(["generate-lockfile", "--offline"]).status().unwrap();
+
+        if !offline.success() {
+            assert!(cargo(&crate_dir).arg("generate-lockfile").status().unwrap().success());
+        }
+    }
+
+    let status = cargo(&crate_dir).args(["test", "--quiet"]).status().unwrap();
+    assert!(status.success(), "tests of generated crate {} failed", name);
+
+    crate_dir
+}
+
+// Build given file as example of generated crate, it has to fail with the message
+fn assert_compile_error(crate_dir: &Path, example_file: &str, message: &str) {
+    let example = Path::new(example_file).file_stem().unwrap().to_str().unwrap();
+
+    std::fs::create_dir_all(crate_dir.join("examples")).unwrap();
+    std::fs::copy(fixture(example_file), crate_dir.join("examples").join(format!("{}.rs", example))).unwrap();
+
+    let output = cargo(crate_dir).args(["build", "--quiet", "--example", example]).output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    // Example is removed so that it doesn't break `cargo test` of the crate on the next run
+    std::fs::remove_dir_all(crate_dir.join("examples")).unwrap();
+
+    assert!(!output.status.success(), "example {} compiled", example);
+    assert!(stderr.contains(message), "example {} failed with other error: {}", example, stderr);
+}
+
+#[test]
+fn collapsed_single_items_read_sample_document() {
+    let builder = Builder::new()
+        .schema_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/schemas"))
+        .schema_dir(fixture("maindoc"))
+        .collapse_single_items(true);
+
+    test_generated("collapsed", builder, "collapse_single_items.rs");
+}
+
+#[test]
+fn schema_features_are_generated_and_validated() {
+    let builder = Builder::new()
+        .schema_dir(fixture("features"))
+        .code_list_dir(fixture("code_lists"));
+
+    test_generated("features", builder, "features.rs");
+}
+
+#[test]
+fn typestate_builder_needs_required_fields() {
+    let builder = Builder::new()
+        .schema_dir(fixture("features"))
+        .code_list_dir(fixture("code_lists"))
+        .typestate_builders(true);
+
+    let crate_dir = test_generated("typestate", builder, "typestate_builders.rs");
+    assert_compile_error(&crate_dir, "typestate_builders/missing_field.rs", "no method named `build` found");
+}
+
+#[test]
+fn reference_newtypes_are_distinct_types() {
+    let builder = Builder::new()
+        .schema_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/schemas"))
+        .reference_newtypes(true);
+
+    let crate_dir = test_generated("newtypes", builder, "reference_newtypes.rs");
+    assert_compile_error(&crate_dir, "reference_newtypes/mixed_up.rs", "mismatched types");
+}